@@ -8,7 +8,7 @@ mod transform_response;
 
 use console_error_panic_hook::set_once;
 use log::Level;
-use repo_icons::{gh_api_get, Readme, RepoIcons};
+use repo_icons::{Readme, RepoIconsClient, RepoIconsOptions, RepoIconsResult};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
@@ -111,15 +111,19 @@ async fn request(req: Request, env: Env, ctx: Context) -> Result<Response> {
     _ => {}
   }
 
-  repo_icons::set_token(
-    token.as_ref().or(
-      env
-        .secret("GITHUB_TOKEN")
-        .ok()
-        .map(|token| token.to_string())
-        .as_ref(),
-    ),
-  );
+  let client = RepoIconsClient::builder()
+    .token(
+      token.as_ref().or(
+        env
+          .secret("GITHUB_TOKEN")
+          .ok()
+          .map(|token| token.to_string())
+          .as_ref(),
+      ),
+    )
+    .user_agent("github-icons")
+    .build()
+    .map_err(|err| Error::RustError(err.to_string()))?;
 
   let cache = Cache::default();
   let cache_bucket = env.bucket("CACHE")?;
@@ -184,9 +188,9 @@ async fn request(req: Request, env: Env, ctx: Context) -> Result<Response> {
     }
   }
 
-  let router = Router::new();
+  let router = Router::with_data(client);
 
-  let npm_handler = async move |req: Request, ctx: RouteContext<()>| {
+  let npm_handler = async move |req: Request, ctx: RouteContext<RepoIconsClient>| {
     if is_navigate(&req) {
       return redirect_to_www(&req, false);
     }
@@ -241,7 +245,12 @@ async fn request(req: Request, env: Env, ctx: Context) -> Result<Response> {
 
       match (
         github_token.starts_with("ghi_"),
-        gh_api_get!("user")
+        RepoIconsClient::builder()
+          .token(Some(&github_token))
+          .user_agent("github-icons")
+          .build()
+          .map_err(|err| Error::RustError(err.to_string()))?
+          .api_get("user")
           .send()
           .await
           .and_then(|res| res.error_for_status()),
@@ -272,62 +281,53 @@ async fn request(req: Request, env: Env, ctx: Context) -> Result<Response> {
         return redirect_to_www(&req, false);
       }
 
-      let mut write_to_cache = true;
       let owner = ctx.param("owner").unwrap().trim_start_matches("@");
-      let repo = ctx.param("repo").unwrap().as_str();
-
-      mixpanel::track("repo_icon", &format!("{}/{}", owner, repo), json!({})).await;
-
-      let result = RepoIcons::load(owner, repo, true).await;
-
-      if let Some(errors) = &result.errors {
-        for error in errors {
-          error!("{}", error);
-        }
-        write_to_cache = false;
-      }
-
-      let repo_icon = match result.icons {
-        Ok(icons) => icons.into_best_match(),
-        Err(err) => return Response::error(err, 404),
-      };
-
-      let mut headers = Headers::new();
-      headers.set("User-Agent", "github-icons")?;
-      for (header_name, header_value) in &repo_icon.headers {
-        headers.set(header_name, header_value)?;
-      }
-
-      let stream = match repo_icon.js_stream().await {
-        Ok(stream) => stream,
-        Err(err) => return Response::error(err.to_string(), 404),
-      };
+      let (repo, git_ref) = split_ref(ctx.param("repo").unwrap());
 
-      let mut res: Response = EdgeResponse::new_with_opt_stream(Some(&stream))?.into();
+      mixpanel::track(
+        "repo_icon",
+        &format!("{}/{}", owner, repo),
+        json!({ "ref": git_ref }),
+      )
+      .await;
 
-      let headers = res.headers_mut();
+      let options = load_options(true, git_ref);
 
-      if write_to_cache {
-        headers.set("Cache-Control", "public, max-age=259200")?;
+      icon_response(ctx.data.load_with_options(owner, repo, &options).await).await
+    })
+    .get_async("/:owner/:repo/tree/*path", async move |req, ctx| {
+      if is_navigate(&req) {
+        return redirect_to_www(&req, false);
       }
 
-      headers.set("Kind", &repo_icon.kind.to_string())?;
+      let owner = ctx.param("owner").unwrap().trim_start_matches("@");
+      let (repo, git_ref) = split_ref(ctx.param("repo").unwrap());
+      let path = ctx.param("path").unwrap().trim_matches('/');
 
-      if let Some(size) = repo_icon.info.size() {
-        headers.set("Size", &size.to_string())?;
-      }
+      mixpanel::track(
+        "repo_subpath_icon",
+        &format!("{}/{}", owner, repo),
+        json!({ "path": path, "ref": git_ref }),
+      )
+      .await;
 
-      headers.set("Content-Type", repo_icon.info.mime_type())?;
+      let options = load_options(true, git_ref).subpath(path);
 
-      Ok(res)
+      icon_response(ctx.data.load_with_options(owner, repo, &options).await).await
     })
     .get_async("/:owner/:repo/all", async move |_, ctx| {
       let owner = ctx.param("owner").unwrap().trim_start_matches("@");
-      let repo = ctx.param("repo").unwrap().as_str();
+      let (repo, git_ref) = split_ref(ctx.param("repo").unwrap());
 
-      mixpanel::track("repo_all_icons", &format!("{}/{}", owner, repo), json!({})).await;
+      mixpanel::track(
+        "repo_all_icons",
+        &format!("{}/{}", owner, repo),
+        json!({ "ref": git_ref }),
+      )
+      .await;
 
-      let result = RepoIcons::load(owner, repo, false).await;
+      let options = load_options(false, git_ref);
+      let result = ctx.data.load_with_options(owner, repo, &options).await;
 
       let mut response = from_json_pretty(&result)?;
 
@@ -338,13 +338,19 @@ async fn request(req: Request, env: Env, ctx: Context) -> Result<Response> {
     })
     .get_async("/:owner/:repo/images", async move |_, ctx| {
       let owner = ctx.param("owner").unwrap().trim_start_matches("@");
-      let repo = ctx.param("repo").unwrap().as_str();
-
-      mixpanel::track("repo_images", &format!("{}/{}", owner, repo), json!({})).await;
-
-      let images = match Readme::load(owner, repo).await {
-        Some(images) => images,
-        None => return Response::error("repo not found", 404),
+      let (repo, git_ref) = split_ref(ctx.param("repo").unwrap());
+
+      mixpanel::track(
+        "repo_images",
+        &format!("{}/{}", owner, repo),
+        json!({ "ref": git_ref }),
+      )
+      .await;
+
+      let images = match Readme::load_with_ref(&ctx.data, owner, repo, git_ref, None).await {
+        Ok(Some(images)) => images,
+        Ok(None) => return Response::error("no images found", 404),
+        Err(err) => return Response::error(err.to_string(), 404),
       };
 
       let mut response = from_json_pretty(&images)?;
@@ -405,6 +411,73 @@ async fn request(req: Request, env: Env, ctx: Context) -> Result<Response> {
   Ok(response)
 }
 
+/// Split `repo@ref` into the repo & the branch, tag or commit to load it at
+fn split_ref(repo: &str) -> (&str, Option<&str>) {
+  match repo.split_once('@') {
+    Some((repo, git_ref)) => (repo, Some(git_ref).filter(|git_ref| !git_ref.is_empty())),
+    None => (repo, None),
+  }
+}
+
+fn load_options(best_matches_only: bool, git_ref: Option<&str>) -> RepoIconsOptions {
+  let options = RepoIconsOptions::new().best_matches_only(best_matches_only);
+
+  match git_ref {
+    Some(git_ref) => options.git_ref(git_ref),
+    None => options,
+  }
+}
+
+/// Stream the best match, only letting it be cached when no source failed transiently
+async fn icon_response(result: RepoIconsResult) -> Result<Response> {
+  let mut write_to_cache = true;
+
+  if let Some(errors) = &result.errors {
+    for error in errors {
+      error!("{}", error);
+
+      // the homepage timing out is expected, and it'd time out again
+      if error.error.is_transient() {
+        write_to_cache = false;
+      }
+    }
+  }
+
+  let repo_icon = match result.icons {
+    Ok(icons) => icons.into_best_match(),
+    Err(err) => return Response::error(err.to_string(), 404),
+  };
+
+  let mut headers = Headers::new();
+  headers.set("User-Agent", "github-icons")?;
+  for (header_name, header_value) in &repo_icon.headers {
+    headers.set(header_name, header_value)?;
+  }
+
+  let stream = match repo_icon.js_stream().await {
+    Ok(stream) => stream,
+    Err(err) => return Response::error(err.to_string(), 404),
+  };
+
+  let mut res: Response = EdgeResponse::new_with_opt_stream(Some(&stream))?.into();
+
+  let headers = res.headers_mut();
+
+  if write_to_cache {
+    headers.set("Cache-Control", "public, max-age=259200")?;
+  }
+
+  headers.set("Kind", &repo_icon.kind.to_string())?;
+
+  if let Some(size) = repo_icon.info.size() {
+    headers.set("Size", &size.to_string())?;
+  }
+
+  headers.set("Content-Type", repo_icon.info.mime_type())?;
+
+  Ok(res)
+}
+
 fn from_json_pretty<B: Serialize>(value: &B) -> Result<Response> {
   let bytes = serialize_json(value)?;
 
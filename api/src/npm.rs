@@ -1,5 +1,5 @@
 use futures::{future::select_all, Future, FutureExt};
-use regex::Regex;
+use repo_icons::RepoSlug;
 use serde_json::Value;
 use std::{error::Error, pin::Pin};
 use worker::{Fetch, Url};
@@ -85,13 +85,12 @@ pub async fn get_slug(package_name: &str) -> Result<String, Box<dyn Error>> {
     futures.remove(index);
 
     if let Ok(repository_url) = repository_url {
-      let re = Regex::new(r"github(\.com)?[/:]([^/]+/[^/]+)").unwrap();
-      let captures = re
-        .captures(&repository_url)
-        .ok_or("not a github repository")?;
+      let slug = repository_url.parse::<RepoSlug>()?;
+      if !slug.is_github_com() {
+        return Err("not a github repository".into());
+      }
 
-      let slug = captures[2].trim_end_matches(".git").to_lowercase();
-      return Ok(slug);
+      return Ok(slug.to_string().to_lowercase());
     }
 
     if futures.is_empty() {
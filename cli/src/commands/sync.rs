@@ -7,7 +7,7 @@ use crate::{
 use diesel::RunQueryDsl;
 use futures::future;
 use parking_lot::RwLock;
-use repo_icons::RepoIcons;
+use repo_icons::{RepoIcons, RepoIconsError};
 use reqwest::Client;
 use site_icons::IconInfo;
 use std::{
@@ -137,12 +137,16 @@ pub async fn sync(slug_or_path: &str) -> Result<(), Box<dyn Error + Send + Sync>
   let icons = RepoIcons::fetch("https://github-icons.com", &user, &repo_name).await;
 
   if let Ok(icons) = icons {
+    // shared so that icons from the same host reuse connections
+    let http = Client::new();
+
     let mut tasks: Vec<_> = icons
       .into_iter()
       .enumerate()
       .map(|(i, icon)| -> JoinHandle<Option<()>> {
         let slug_or_path = slug_or_path.to_string();
         let (user, repo_name) = (user.clone(), repo_name.clone());
+        let http = http.clone();
 
         tokio::spawn(async move {
           let cache_name = format!("{}{}", icon.url.host_str().unwrap_or(""), icon.url.path())
@@ -192,7 +196,7 @@ pub async fn sync(slug_or_path: &str) -> Result<(), Box<dyn Error + Send + Sync>
                 }
               }
               _ => {
-                let response = Client::new()
+                let response = http
                   .get(icon.url)
                   .headers((&icon.headers).try_into().unwrap())
                   .send()
@@ -241,9 +245,12 @@ pub async fn sync(slug_or_path: &str) -> Result<(), Box<dyn Error + Send + Sync>
       }
     }
   } else {
-    let error = format!("{:?}", icons);
+    let error = icons.unwrap_err();
 
-    if error.contains("403") {
+    if matches!(
+      error,
+      RepoIconsError::RateLimited { .. } | RepoIconsError::Http { status: 403, .. }
+    ) {
       eprintln!("Error: Rate limited, please provide a token");
       exit(1);
     } else {
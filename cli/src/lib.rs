@@ -16,6 +16,7 @@ use database::db;
 use diesel::prelude::*;
 
 use once_cell::sync::Lazy;
+use repo_icons::RepoSlug;
 use std::{
   error::Error,
   fs::create_dir,
@@ -24,7 +25,6 @@ use std::{
   process::Command,
 };
 use tokio::fs;
-use url::Url;
 
 static CACHE_DIR: Lazy<PathBuf> = Lazy::new(|| {
   let path = Path::new(&home::home_dir().unwrap()).join("Library/Caches/com.samdenty.github-icons");
@@ -80,42 +80,21 @@ pub async fn list_repos() -> Result<Vec<Repo>, Box<dyn Error>> {
 }
 
 fn get_slug(repo: &str) -> Result<(String, String, Option<String>), Box<dyn Error + Send + Sync>> {
-  if repo.split("/").count() == 2 && !Path::new(&repo).exists() {
-    let mut slug = repo.split("/");
+  if !Path::new(&repo).exists() {
+    let slug = repo.parse::<RepoSlug>()?;
 
-    let user = slug.next().unwrap().to_string();
-    let repo = slug.next().unwrap().to_string();
-
-    Ok((user, repo, None))
+    Ok((slug.owner, slug.repo, None))
   } else {
     let output = Command::new("git")
       .args(["config", "--get", "remote.origin.url"])
       .current_dir(Path::new(repo))
       .output()?;
 
-    let url = Url::parse(&String::from_utf8(output.stdout)?)
+    // remotes can be ssh, such as git@github.com:owner/repo.git
+    let slug = String::from_utf8(output.stdout)?
+      .parse::<RepoSlug>()
       .map_err(|_| std::io::Error::new(ErrorKind::Other, "No repository found for folder"))?;
-    let slug = url.path().strip_suffix(".git").unwrap_or(url.path());
-    let mut slug = slug.split("/");
-
-    slug.next();
-
-    let user = slug
-      .next()
-      .ok_or(std::io::Error::new(
-        ErrorKind::Other,
-        "No user in repo url found",
-      ))?
-      .to_string();
-
-    let repo_name = slug
-      .next()
-      .ok_or(std::io::Error::new(
-        ErrorKind::Other,
-        "No repo in repo url found",
-      ))?
-      .to_string();
-
-    Ok((user, repo_name, Some(repo.to_string())))
+
+    Ok((slug.owner, slug.repo, Some(repo.to_string())))
   }
 }
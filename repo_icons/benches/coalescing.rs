@@ -0,0 +1,181 @@
+//! Counts the round trips made to a local mock of the GitHub API, showing what
+//! single-flight loads & the shared connection pool save.
+//!
+//! ```sh
+//! cargo bench --bench coalescing
+//! ```
+
+use futures::future::join_all;
+use repo_icons::{ForgeHost, RepoIconsClient};
+use serde_json::json;
+use std::{
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+use tokio::{
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+  net::{TcpListener, TcpStream},
+  time::sleep,
+};
+use url::Url;
+
+/// How many loads each scenario makes
+const LOADS: usize = 50;
+
+/// Delay before the mock responds, so that concurrent loads overlap
+const LATENCY: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Default)]
+struct Counts {
+  requests: Arc<AtomicUsize>,
+  connections: Arc<AtomicUsize>,
+}
+
+impl Counts {
+  fn take(&self) -> (usize, usize) {
+    (
+      self.requests.swap(0, Ordering::SeqCst),
+      self.connections.swap(0, Ordering::SeqCst),
+    )
+  }
+}
+
+/// A GitHub Enterprise Server that serves `repos/{owner}/{repo}` over keep-alive connections
+async fn start_mock_server(counts: Counts) -> Url {
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+  tokio::spawn(async move {
+    loop {
+      let (socket, _) = listener.accept().await.unwrap();
+      counts.connections.fetch_add(1, Ordering::SeqCst);
+      tokio::spawn(serve(socket, counts.clone()));
+    }
+  });
+
+  url
+}
+
+async fn serve(socket: TcpStream, counts: Counts) {
+  let (reader, mut writer) = socket.into_split();
+  let mut reader = BufReader::new(reader);
+
+  loop {
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+      return;
+    }
+
+    // GET requests don't have a body, so the request ends with the headers
+    loop {
+      let mut header = String::new();
+      if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+        return;
+      }
+      if header == "\r\n" {
+        break;
+      }
+    }
+
+    counts.requests.fetch_add(1, Ordering::SeqCst);
+    sleep(LATENCY).await;
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let mut segments = path.trim_start_matches("/api/v3/repos/").split('/');
+    let owner = segments.next().unwrap_or_default();
+    let repo = segments.next().unwrap_or_default();
+
+    let body = json!({
+      "owner": { "login": owner, "type": "User" },
+      "name": repo,
+      "default_branch": "main",
+      "private": false,
+      "homepage": null,
+    })
+    .to_string();
+
+    let response = format!(
+      "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+      body.len(),
+      body
+    );
+    if writer.write_all(response.as_bytes()).await.is_err() {
+      return;
+    }
+  }
+}
+
+fn client(url: &Url) -> RepoIconsClient {
+  RepoIconsClient::builder()
+    .token(None::<String>)
+    .host(ForgeHost::github_enterprise(url.clone()))
+    .build()
+    .unwrap()
+}
+
+fn report(name: &str, start: Instant, counts: &Counts) {
+  let (requests, connections) = counts.take();
+
+  println!(
+    "{:<48} {:>4} requests {:>4} connections {:>8.1?}",
+    name,
+    requests,
+    connections,
+    start.elapsed()
+  );
+}
+
+#[tokio::main]
+async fn main() {
+  let counts = Counts::default();
+  let url = start_mock_server(counts.clone()).await;
+
+  println!("{} loads, {:?} of latency per request\n", LOADS, LATENCY);
+
+  // every source of a load asks for the repo at the same time
+  let shared = client(&url);
+  let start = Instant::now();
+  join_all((0..LOADS).map(|_| shared.repo("facebook", "react"))).await;
+  report("concurrent loads of one repo", start, &counts);
+
+  // separate clients don't share caches, so nothing is coalesced
+  let clients = (0..LOADS).map(|_| client(&url)).collect::<Vec<_>>();
+  let start = Instant::now();
+  join_all(
+    clients
+      .iter()
+      .map(|client| client.repo("facebook", "react")),
+  )
+  .await;
+  report(
+    "concurrent loads of one repo, separate caches",
+    start,
+    &counts,
+  );
+
+  let start = Instant::now();
+  for i in 0..LOADS {
+    client(&url)
+      .repo("facebook", &format!("repo-{}", i))
+      .await
+      .unwrap();
+  }
+  report("sequential loads, shared connection pool", start, &counts);
+
+  let start = Instant::now();
+  for i in 0..LOADS {
+    RepoIconsClient::builder()
+      .token(None::<String>)
+      .host(ForgeHost::github_enterprise(url.clone()))
+      .http_client(reqwest::Client::new())
+      .build()
+      .unwrap()
+      .repo("facebook", &format!("repo-{}", i))
+      .await
+      .unwrap();
+  }
+  report("sequential loads, new HTTP client per load", start, &counts);
+}
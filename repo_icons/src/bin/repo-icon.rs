@@ -1,11 +1,13 @@
 use clap::Parser;
 use env_logger::Builder;
 use log::LevelFilter;
-use repo_icons::{set_token, RepoIcons};
-use std::error::Error;
+use repo_icons::{FileCache, Forge, ForgeHost, RepoIconsClient, RepoIconsOptions, RepoSlug};
+use std::{error::Error, path::PathBuf};
+use url::Url;
 
 #[derive(Parser)]
 struct Opts {
+  /// owner/repo, a repo url, git remote or shorthand such as hf:owner/name
   slug: String,
   #[clap(long)]
   json: bool,
@@ -15,13 +17,15 @@ struct Opts {
   #[clap(long)]
   /// Use a github token to get icons for private repos
   token: Option<String>,
-}
-
-macro_rules! regex {
-  ($re:literal $(,)?) => {{
-    static RE: once_cell::sync::OnceCell<fancy_regex::Regex> = once_cell::sync::OnceCell::new();
-    RE.get_or_init(|| fancy_regex::Regex::new($re).unwrap())
-  }};
+  #[clap(long)]
+  /// Url of the forge to load the repo from, such as a GitHub Enterprise Server
+  host: Option<Url>,
+  #[clap(long)]
+  /// The forge running on the host (github, gitlab, gitea, bitbucket, huggingface), detected for well known hosts
+  forge: Option<Forge>,
+  #[clap(long)]
+  /// Cache API responses in a directory, so that they're reused between runs
+  cache_dir: Option<PathBuf>,
 }
 
 #[tokio::main]
@@ -34,16 +38,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
     builder.init();
   }
 
-  set_token(opts.token);
+  // the host of the slug is used, unless it's overridden
+  let slug = opts.slug.parse::<RepoSlug>()?;
+  let host = match (opts.host.or(slug.host), opts.forge) {
+    (Some(host), Some(forge)) => ForgeHost::with_forge(forge, host),
+    (Some(host), None) => ForgeHost::detect(&host).unwrap_or(ForgeHost::github_enterprise(host)),
+    (None, forge) => ForgeHost::public(forge.unwrap_or(Forge::Github)),
+  };
 
-  let slug = regex!("([^/]+)/(.+)")
-    .captures(&opts.slug)
-    .unwrap()
-    .unwrap();
-  let user = &slug[1];
-  let repo = &slug[2];
+  let mut client = RepoIconsClient::builder().token(opts.token).host(host);
+  if let Some(cache_dir) = opts.cache_dir {
+    client = client.cache(FileCache::new(cache_dir));
+  }
+  let client = client.build()?;
 
-  let icons = RepoIcons::load(user, repo, true).await.icons?;
+  let icons = client
+    .load_slug(&opts.slug, &RepoIconsOptions::new().best_matches_only(true))
+    .await
+    .icons?;
   let icon = icons.best_match();
 
   if opts.json {
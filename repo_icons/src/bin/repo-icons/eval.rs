@@ -0,0 +1,300 @@
+use clap::Args;
+use futures::{stream, StreamExt};
+use repo_icons::{
+  FixtureBackend, RecordBackend, RepoIconKind, RepoIconsClient, RepoIconsOptions, RepoSlug,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+  collections::{BTreeMap, HashMap},
+  error::Error,
+  fs,
+  path::PathBuf,
+};
+use url::Url;
+
+/// How many repos are loaded at the same time
+const CONCURRENCY: usize = 8;
+
+#[derive(Args)]
+pub struct EvalOpts {
+  /// JSON file of labelled repos, such as eval/corpus.json
+  corpus: PathBuf,
+  #[clap(long, default_value = "tests/fixtures")]
+  /// Directory with the recorded responses of each repo
+  fixtures: PathBuf,
+  #[clap(long)]
+  /// Load the repos from the network, recording their fixtures
+  record: bool,
+  #[clap(long)]
+  /// Use a github token when recording
+  token: Option<String>,
+  #[clap(long)]
+  /// Save the results, so that a later ranking can be compared against them
+  output: Option<PathBuf>,
+  #[clap(long)]
+  /// Results saved by an earlier run, to show which repos changed
+  baseline: Option<PathBuf>,
+}
+
+/// The icon the real repo should get, picked by hand. Any of them can be set
+#[derive(Clone, Serialize, Deserialize)]
+struct Label {
+  slug: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  url: Option<Url>,
+  /// such as `icon_field` or `avatar`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  kind: Option<String>,
+  /// of icons committed to the repo, as their urls change with every commit
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  path: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Outcome {
+  expected: Label,
+  /// of the best match
+  kind: Option<String>,
+  url: Option<Url>,
+  #[serde(default)]
+  path: Option<String>,
+  error: Option<String>,
+  /// whether there were fixtures to load the repo from
+  #[serde(default = "recorded")]
+  recorded: bool,
+}
+
+fn recorded() -> bool {
+  true
+}
+
+impl Label {
+  fn matches(&self, kind: &str, url: &Url, path: Option<&str>) -> bool {
+    self
+      .kind
+      .as_deref()
+      .map_or(true, |expected| expected == kind)
+      && self.url.as_ref().map_or(true, |expected| expected == url)
+      && self
+        .path
+        .as_deref()
+        .map_or(true, |expected| Some(expected) == path)
+  }
+}
+
+impl Outcome {
+  fn is_correct(&self) -> bool {
+    match (&self.kind, &self.url) {
+      (Some(kind), Some(url)) => self.expected.matches(kind, url, self.path.as_deref()),
+      _ => false,
+    }
+  }
+
+  fn summary(&self) -> String {
+    match (&self.kind, &self.url, &self.error) {
+      (Some(kind), Some(url), _) => format!("{} {}", kind, url),
+      (_, _, Some(error)) => format!("error: {}", error),
+      _ => "no icons".to_string(),
+    }
+  }
+}
+
+pub async fn run(opts: EvalOpts) -> Result<(), Box<dyn Error>> {
+  let corpus = serde_json::from_str::<Vec<Label>>(&fs::read_to_string(&opts.corpus)?)?;
+
+  let outcomes = stream::iter(corpus)
+    .map(|label| evaluate(&opts, label))
+    .buffered(CONCURRENCY)
+    .collect::<Vec<_>>()
+    .await;
+
+  // scoring repos without fixtures would only measure what's missing
+  let unrecorded = outcomes
+    .iter()
+    .filter(|outcome| !outcome.recorded)
+    .map(|outcome| outcome.expected.slug.as_str())
+    .collect::<Vec<_>>();
+  if !unrecorded.is_empty() {
+    println!(
+      "not scoring the corpus, as these repos haven't been recorded (see --record):\n  {}",
+      unrecorded.join("\n  ")
+    );
+    return Ok(());
+  }
+
+  report(&outcomes);
+
+  if let Some(baseline) = &opts.baseline {
+    let baseline = serde_json::from_str::<Vec<Outcome>>(&fs::read_to_string(baseline)?)?;
+    report_changes(&baseline, &outcomes);
+  }
+
+  if let Some(output) = &opts.output {
+    fs::write(output, serde_json::to_string_pretty(&outcomes)?)?;
+  }
+
+  Ok(())
+}
+
+async fn evaluate(opts: &EvalOpts, label: Label) -> Outcome {
+  let outcome = |kind, url, path, error| Outcome {
+    expected: label.clone(),
+    kind,
+    url,
+    path,
+    error,
+    recorded: true,
+  };
+
+  let slug = match label.slug.parse::<RepoSlug>() {
+    Ok(slug) => slug,
+    Err(err) => return outcome(None, None, None, Some(err.to_string())),
+  };
+
+  // the same layout as the fixtures of the tests
+  let dir = opts.fixtures.join(format!("{}-{}", slug.owner, slug.repo));
+  if !opts.record && !dir.is_dir() {
+    return Outcome {
+      recorded: false,
+      ..outcome(None, None, None, None)
+    };
+  }
+
+  let builder = RepoIconsClient::builder();
+  let client = if opts.record {
+    builder
+      .token(opts.token.clone())
+      .backend(RecordBackend::new(dir))
+  } else {
+    builder
+      .token(None::<String>)
+      .backend(FixtureBackend::new(dir))
+  }
+  .build();
+  let client = match client {
+    Ok(client) => client,
+    Err(err) => return outcome(None, None, None, Some(err.to_string())),
+  };
+
+  match client
+    .load_slug(&label.slug, &RepoIconsOptions::new())
+    .await
+    .icons
+  {
+    Ok(icons) => {
+      let icon = icons.best_match();
+      let path = match &icon.kind {
+        RepoIconKind::IconField { file }
+        | RepoIconKind::RepoFile { file }
+        | RepoIconKind::Framework { file, .. } => Some(file.path.clone()),
+        _ => None,
+      };
+
+      outcome(
+        Some(icon.kind.to_string()),
+        Some(icon.url.clone()),
+        path,
+        None,
+      )
+    }
+    Err(err) => outcome(None, None, None, Some(err.to_string())),
+  }
+}
+
+fn report(outcomes: &[Outcome]) {
+  for outcome in outcomes.iter().filter(|outcome| !outcome.is_correct()) {
+    let expected = &outcome.expected;
+    println!("✗ {}", expected.slug);
+    println!(
+      "    expected {} {}{}",
+      expected.kind.as_deref().unwrap_or("*"),
+      expected
+        .url
+        .as_ref()
+        .map_or("*".to_string(), Url::to_string),
+      expected
+        .path
+        .as_ref()
+        .map_or(String::new(), |path| format!(" ({})", path))
+    );
+    println!("    got      {}", outcome.summary());
+  }
+
+  let correct = outcomes
+    .iter()
+    .filter(|outcome| outcome.is_correct())
+    .count();
+  println!(
+    "\ntop-1 accuracy: {}/{} ({:.1}%)",
+    correct,
+    outcomes.len(),
+    100.0 * correct as f64 / outcomes.len().max(1) as f64
+  );
+
+  // labels without a kind can only be judged by their url
+  let mut confusion = BTreeMap::<(&str, &str), usize>::new();
+  for outcome in outcomes {
+    if let Some(expected) = &outcome.expected.kind {
+      let actual = outcome.kind.as_deref().unwrap_or("none");
+      *confusion.entry((expected.as_str(), actual)).or_default() += 1;
+    }
+  }
+
+  if !confusion.is_empty() {
+    println!("\nexpected kind -> best match kind:");
+    for ((expected, actual), count) in confusion {
+      let marker = if expected == actual { " " } else { "✗" };
+      println!("  {} {:<22} -> {:<22} {}", marker, expected, actual, count);
+    }
+  }
+}
+
+/// Compare the best matches against the ones of an earlier ranking
+fn report_changes(baseline: &[Outcome], outcomes: &[Outcome]) {
+  let baseline = baseline
+    .iter()
+    .map(|outcome| (outcome.expected.slug.as_str(), outcome))
+    .collect::<HashMap<_, _>>();
+
+  let mut changes = Vec::new();
+  for outcome in outcomes {
+    let before = match baseline.get(outcome.expected.slug.as_str()) {
+      Some(before) => before,
+      None => continue,
+    };
+
+    let (before_summary, after_summary) = (before.summary(), outcome.summary());
+    if before_summary == after_summary {
+      continue;
+    }
+
+    let marker = match (before.is_correct(), outcome.is_correct()) {
+      (false, true) => "fixed",
+      (true, false) => "regressed",
+      _ => "changed",
+    };
+    changes.push(format!(
+      "  {:<9} {}\n    before {}\n    after  {}",
+      marker, outcome.expected.slug, before_summary, after_summary
+    ));
+  }
+
+  let before = baseline
+    .values()
+    .filter(|outcome| outcome.is_correct())
+    .count();
+  let after = outcomes
+    .iter()
+    .filter(|outcome| outcome.is_correct())
+    .count();
+
+  println!(
+    "\n{} repos changed since the baseline, {} -> {} correct",
+    changes.len(),
+    before,
+    after
+  );
+  for change in changes {
+    println!("{}", change);
+  }
+}
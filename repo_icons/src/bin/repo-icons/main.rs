@@ -0,0 +1,100 @@
+mod eval;
+
+use clap::{Parser, Subcommand};
+use env_logger::Builder;
+use log::LevelFilter;
+use repo_icons::{FileCache, Forge, ForgeHost, RepoIconsClient, RepoIconsOptions, RepoSlug};
+use std::{error::Error, path::PathBuf};
+use url::Url;
+
+#[derive(Parser)]
+#[clap(subcommand_negates_reqs = true)]
+struct Opts {
+  #[clap(subcommand)]
+  command: Option<Command>,
+  #[clap(required = true)]
+  /// owner/repo, a repo url, git remote or shorthand such as hf:owner/name
+  slug: Option<String>,
+  #[clap(long)]
+  json: bool,
+  #[clap(long)]
+  /// Print out errors that occurred for skipped items
+  debug: bool,
+  #[clap(long)]
+  /// Use a github token to get icons for private repos
+  token: Option<String>,
+  #[clap(long)]
+  /// Url of the forge to load the repo from, such as a GitHub Enterprise Server
+  host: Option<Url>,
+  #[clap(long)]
+  /// The forge running on the host (github, gitlab, gitea, bitbucket, huggingface), detected for well known hosts
+  forge: Option<Forge>,
+  #[clap(long)]
+  /// Cache API responses in a directory, so that they're reused between runs
+  cache_dir: Option<PathBuf>,
+  #[clap(long)]
+  /// Print out why each icon was ranked where it was, & the candidates that were rejected
+  explain: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+  /// Measure how often the best match is the expected icon, over a labelled corpus of repos
+  Eval(eval::EvalOpts),
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+  env_logger::init();
+
+  let opts: Opts = Opts::parse();
+
+  if opts.debug {
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info);
+    builder.init();
+  }
+
+  let slug_arg = match (opts.command, opts.slug) {
+    (Some(Command::Eval(eval_opts)), _) => return eval::run(eval_opts).await,
+    (None, Some(slug)) => slug,
+    (None, None) => unreachable!("the slug is required without a subcommand"),
+  };
+
+  // the host of the slug is used, unless it's overridden
+  let slug = slug_arg.parse::<RepoSlug>()?;
+  let host = match (opts.host.or(slug.host), opts.forge) {
+    (Some(host), Some(forge)) => ForgeHost::with_forge(forge, host),
+    (Some(host), None) => ForgeHost::detect(&host).unwrap_or(ForgeHost::github_enterprise(host)),
+    (None, forge) => ForgeHost::public(forge.unwrap_or(Forge::Github)),
+  };
+
+  let mut client = RepoIconsClient::builder().token(opts.token).host(host);
+  if let Some(cache_dir) = opts.cache_dir {
+    client = client.cache(FileCache::new(cache_dir));
+  }
+  let client = client.build()?;
+
+  let result = client
+    .load_slug(&slug_arg, &RepoIconsOptions::new().explain(opts.explain))
+    .await;
+
+  if opts.json {
+    println!("{}", serde_json::to_string_pretty(&result)?)
+  } else {
+    // shown before the icons, as they're still worth seeing when none were found
+    for rejected in &result.rejected {
+      println!("{}", rejected);
+    }
+
+    for icon in result.icons? {
+      println!("{} {} {}", icon.url, icon.kind, icon.info);
+
+      if let Some(explanation) = &icon.explanation {
+        print!("{}", explanation);
+      }
+    }
+  }
+
+  Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::ForgeHost;
 use fancy_regex::Regex;
 use once_cell::sync::Lazy;
 use url::Url;
@@ -76,7 +77,17 @@ static BLACKLISTED_HOMEPAGES: Lazy<Vec<Regex>> = Lazy::new(|| {
   .to_vec()
 });
 
-pub fn is_badge_url(url: &Url) -> bool {
+pub fn is_badge_url(host: &ForgeHost, url: &Url) -> bool {
+  // github actions status badges, on the forge the repo is loaded from
+  if let Some(path) = host.web_path(url) {
+    if regex!("^[^/]+/[^/]+/(actions/)?workflows")
+      .is_match(path)
+      .unwrap()
+    {
+      return true;
+    }
+  }
+
   let domain = if let Some(domain) = url.domain() {
     domain
   } else {
@@ -95,7 +106,12 @@ pub fn is_badge_text(string: &str) -> bool {
     .any(|url_regex| url_regex.is_match(&string).unwrap())
 }
 
-pub fn is_blacklisted_homepage(url: &Url) -> bool {
+pub fn is_blacklisted_homepage(host: &ForgeHost, url: &Url) -> bool {
+  // links back to the repo (or anywhere else on the same forge)
+  if host.web_path(url).is_some() {
+    return true;
+  }
+
   let domain = if let Some(domain) = url.domain() {
     domain
   } else {
@@ -107,3 +123,39 @@ pub fn is_blacklisted_homepage(url: &Url) -> bool {
     .iter()
     .any(|url_regex| url_regex.is_match(&url).unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn url(url: &str) -> Url {
+    Url::parse(url).unwrap()
+  }
+
+  #[test]
+  fn github_com_is_blacklisted_on_every_forge() {
+    let ghes = ForgeHost::github_enterprise(url("https://git.corp.example/"));
+    let gitlab = ForgeHost::gitlab(url("https://gitlab.com/"));
+
+    for host in [ForgeHost::github(), ghes.clone(), gitlab] {
+      assert!(is_blacklisted_homepage(
+        &host,
+        &url("https://github.com/facebook/react")
+      ));
+      assert!(is_badge_url(
+        &host,
+        &url("https://github.com/facebook/react/workflows/CI/icon.svg")
+      ));
+    }
+
+    assert!(is_blacklisted_homepage(
+      &ghes,
+      &url("https://git.corp.example/team/app")
+    ));
+    assert!(is_badge_url(
+      &ghes,
+      &url("https://git.corp.example/team/app/actions/workflows/ci.yml/icon.svg")
+    ));
+    assert!(!is_blacklisted_homepage(&ghes, &url("https://react.dev/")));
+  }
+}
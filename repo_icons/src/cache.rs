@@ -0,0 +1,147 @@
+use crate::{MaybeSend, MaybeSync};
+use cached::{Cached, SizedCache};
+use instant::Duration;
+use std::sync::{Arc, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::PathBuf};
+
+/// The kinds of API responses that get cached, each with their own TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheKind {
+  /// Metadata of a repo, such as its homepage & default branch
+  Repo,
+  /// The files in a repo, at a branch, tag or commit
+  RepoFiles,
+  /// Who owns a repo after renames, & whether they're an organization
+  RedirectedUser,
+  /// The rendered root readme of a GitHub repo
+  Readme,
+}
+
+impl CacheKind {
+  pub fn name(&self) -> &'static str {
+    match self {
+      CacheKind::Repo => "repo",
+      CacheKind::RepoFiles => "repo_files",
+      CacheKind::RedirectedUser => "redirected_user",
+      CacheKind::Readme => "readme",
+    }
+  }
+
+  /// How long entries are kept when no TTL has been set for the kind
+  pub fn default_ttl(&self) -> Duration {
+    match self {
+      CacheKind::RedirectedUser => Duration::from_secs(24 * 60 * 60),
+      _ => Duration::from_secs(60 * 60),
+    }
+  }
+}
+
+/// Stores API responses between loads, for longer than a [`RepoIconsClient`](crate::RepoIconsClient)
+/// lives. Keys look like `github.com/repo/facebook/react`, & values are JSON that records
+/// when it expires, so entries can be kept for longer than their TTL.
+///
+/// Private repos are cached too, so only share a cache between clients with the same token
+///
+/// ```
+/// let client = RepoIconsClient::builder()
+///   .cache(FileCache::new("/tmp/repo_icons"))
+///   .cache_ttl(CacheKind::RepoFiles, Duration::from_secs(24 * 60 * 60))
+///   .build()?;
+/// ```
+pub trait Cache: MaybeSend + MaybeSync {
+  fn get(&self, key: &str) -> Option<String>;
+
+  /// `ttl` is how long the value is fresh for. Expired values are revalidated
+  /// with the ETag they were stored with, so stores shouldn't evict them straight away
+  fn set(&self, key: &str, value: String, ttl: Duration);
+}
+
+impl<C: Cache + ?Sized> Cache for Arc<C> {
+  fn get(&self, key: &str) -> Option<String> {
+    (**self).get(key)
+  }
+
+  fn set(&self, key: &str, value: String, ttl: Duration) {
+    (**self).set(key, value, ttl)
+  }
+}
+
+/// Doesn't store anything, responses are only kept in the memory of each client.
+/// This is the default
+pub struct NoopCache;
+
+impl Cache for NoopCache {
+  fn get(&self, _key: &str) -> Option<String> {
+    None
+  }
+
+  fn set(&self, _key: &str, _value: String, _ttl: Duration) {}
+}
+
+/// Keeps the most recently used responses in memory,
+/// which can be shared between clients with an `Arc`
+pub struct MemoryCache(Mutex<SizedCache<String, String>>);
+
+impl MemoryCache {
+  pub fn new(size: usize) -> Self {
+    Self(Mutex::new(SizedCache::with_size(size)))
+  }
+}
+
+impl Default for MemoryCache {
+  fn default() -> Self {
+    Self::new(1000)
+  }
+}
+
+impl Cache for MemoryCache {
+  fn get(&self, key: &str) -> Option<String> {
+    self.0.lock().unwrap().cache_get(key).cloned()
+  }
+
+  fn set(&self, key: &str, value: String, _ttl: Duration) {
+    self.0.lock().unwrap().cache_set(key.to_string(), value);
+  }
+}
+
+/// Stores each response as a file in a directory, so that they're kept between runs.
+/// Expired files are revalidated the next time they're loaded. Not supported on wasm
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileCache {
+  dir: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileCache {
+  pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+    Self { dir: dir.into() }
+  }
+
+  fn path(&self, key: &str) -> PathBuf {
+    self
+      .dir
+      .join(format!("{}.json", crate::forges::encode(key)))
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Cache for FileCache {
+  fn get(&self, key: &str) -> Option<String> {
+    fs::read_to_string(self.path(key)).ok()
+  }
+
+  fn set(&self, key: &str, value: String, _ttl: Duration) {
+    let path = self.path(key);
+    // written to a temporary file first, so that readers never see half of it
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+
+    let res = fs::create_dir_all(&self.dir)
+      .and_then(|_| fs::write(&temp_path, value))
+      .and_then(|_| fs::rename(&temp_path, &path));
+
+    if let Err(err) = res {
+      warn!("failed to cache {} in {}: {}", key, self.dir.display(), err);
+    }
+  }
+}
@@ -0,0 +1,406 @@
+use crate::{
+  forges::hugging_face,
+  get_token,
+  github_api::{GithubBackend, GithubCaches, HttpBackend, RateLimit, Readme, Repo, RetryPolicy},
+  BoxStream, Cache, CacheKind, Forge, ForgeHost, NoopCache, RepoIcons, RepoIconsError,
+  RepoIconsEvent, RepoIconsOptions, RepoIconsResult, RepoSlug,
+};
+use cached::{Cached, SizedCache};
+use instant::Duration;
+use once_cell::sync::Lazy;
+use reqwest::{
+  header::{HeaderMap, HeaderValue, USER_AGENT},
+  Client, IntoUrl, RequestBuilder,
+};
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+use url::Url;
+
+const DEFAULT_USER_AGENT: &str = "repo_icons";
+
+static GLOBAL_CLIENT: Lazy<RepoIconsClient> =
+  Lazy::new(|| RepoIconsClient::builder().build().unwrap());
+
+/// HTTP clients by user agent, so that every [`RepoIconsClient`] (such as the ones
+/// built for each forge) shares the same connection pool
+static HTTP_CLIENTS: Lazy<Mutex<HashMap<String, Client>>> = Lazy::new(Default::default);
+
+static HUGGING_FACE_CLIENT: Lazy<RepoIconsClient> = Lazy::new(|| {
+  RepoIconsClient::builder()
+    .token(None::<String>)
+    .host(ForgeHost::hugging_face())
+    .build()
+    .unwrap()
+});
+
+/// Clients for the other forges that slugs point to, by their website,
+/// so that loads from the same forge share their caches
+static FORGE_CLIENTS: Lazy<Mutex<SizedCache<Url, RepoIconsClient>>> =
+  Lazy::new(|| Mutex::new(SizedCache::with_size(32)));
+
+#[derive(Clone)]
+enum Token {
+  /// Use whatever was passed to [`set_token`](crate::set_token)
+  Global,
+  Explicit(Option<String>),
+}
+
+/// Owns everything needed to load icons on behalf of a single user:
+/// their GitHub token, a pooled HTTP client & the API caches.
+/// Cloning it is cheap, and clones share the same caches
+///
+/// ```
+/// let client = RepoIconsClient::builder()
+///   .token(Some("ghp_..."))
+///   .user_agent("my-app")
+///   .build()?;
+///
+/// let icons = client.load("facebook", "react", false).await;
+/// ```
+#[derive(Clone)]
+pub struct RepoIconsClient(Arc<ClientInner>);
+
+struct ClientInner {
+  token: Token,
+  http: Client,
+  user_agent: String,
+  host: ForgeHost,
+  retry_policy: RetryPolicy,
+  backend: Arc<dyn GithubBackend>,
+  rate_limit: Mutex<Option<RateLimit>>,
+  caches: GithubCaches,
+}
+
+impl RepoIconsClient {
+  pub fn builder() -> RepoIconsClientBuilder {
+    RepoIconsClientBuilder::default()
+  }
+
+  /// The client used by the free functions, such as [`RepoIcons::load`].
+  /// Its token is whatever was last passed to [`set_token`](crate::set_token)
+  pub fn global() -> &'static RepoIconsClient {
+    &GLOBAL_CLIENT
+  }
+
+  /// The client used by the free functions for Hugging Face slugs.
+  /// Unauthenticated, as the global token is meant for GitHub
+  pub fn hugging_face() -> &'static RepoIconsClient {
+    &HUGGING_FACE_CLIENT
+  }
+
+  /// The client used by the free functions for slugs on other forges, such as
+  /// a GitLab instance. Unauthenticated, as the global token is meant for GitHub
+  pub fn for_host(host: &ForgeHost) -> RepoIconsClient {
+    if host.forge() == Forge::HuggingFace {
+      return Self::hugging_face().clone();
+    }
+
+    FORGE_CLIENTS
+      .lock()
+      .unwrap()
+      .cache_get_or_set_with(host.web().clone(), || {
+        RepoIconsClient::builder()
+          .token(None::<String>)
+          .host(host.clone())
+          .build()
+          .unwrap()
+      })
+      .clone()
+  }
+
+  /// Fetch all the icons, see [`RepoIcons::load`]
+  pub async fn load(&self, owner: &str, repo: &str, best_matches_only: bool) -> RepoIconsResult {
+    self
+      .load_with_options(
+        owner,
+        repo,
+        &RepoIconsOptions::new().best_matches_only(best_matches_only),
+      )
+      .await
+  }
+
+  /// Fetch all the icons, see [`RepoIcons::load_with_options`]
+  pub async fn load_with_options(
+    &self,
+    owner: &str,
+    repo: &str,
+    options: &RepoIconsOptions,
+  ) -> RepoIconsResult {
+    RepoIcons::load_with_client(self, owner, repo, options).await
+  }
+
+  /// Stream the icons of each source as they load, see [`RepoIcons::stream`]
+  pub fn stream<'a>(
+    &'a self,
+    owner: &'a str,
+    repo: &'a str,
+    options: &'a RepoIconsOptions,
+  ) -> BoxStream<'a, RepoIconsEvent> {
+    RepoIcons::stream_with_client(self, owner, repo, options)
+  }
+
+  /// Fetch all the icons for a [`RepoSlug`], such as `owner/repo` or a repo url.
+  /// The ref & path of tree urls are used, unless the options set them
+  pub async fn load_slug(&self, slug: &str, options: &RepoIconsOptions) -> RepoIconsResult {
+    let slug = match slug.parse::<RepoSlug>() {
+      Ok(slug) => slug,
+      Err(err) => {
+        return RepoIconsResult {
+          errors: None,
+          icons: Err(err),
+          rejected: Vec::new(),
+        }
+      }
+    };
+
+    match self.resolve_slug(&slug).await {
+      Ok((owner, repo)) => {
+        let git_ref = options.git_ref.as_deref().or(slug.commitish.as_deref());
+        let subpath = options.subpath.as_deref().or(slug.path.as_deref());
+        RepoIcons::load_at(self, &owner, &repo, git_ref, subpath, options).await
+      }
+      Err(err) => RepoIconsResult {
+        errors: None,
+        icons: Err(err),
+        rejected: Vec::new(),
+      },
+    }
+  }
+
+  /// Fetch the icons of many repos at once, see [`RepoIcons::load_many`]
+  pub async fn load_many<S: AsRef<str>>(
+    &self,
+    slugs: &[S],
+    options: &RepoIconsOptions,
+  ) -> Vec<RepoIconsResult> {
+    RepoIcons::load_many_with(self, slugs, options, |slug| self.load_slug(slug, options)).await
+  }
+
+  /// The owner & repo to load a slug with. On Hugging Face,
+  /// `hf:owner/name` is resolved to the model, space or dataset with that name
+  pub async fn resolve_slug(&self, slug: &RepoSlug) -> Result<(String, String), RepoIconsError> {
+    if let Some(host) = slug.forge_host() {
+      if host.web() != self.host().web() {
+        return Err(RepoIconsError::other(format!(
+          "{} is on {}, but the client loads from {}",
+          slug,
+          host.web(),
+          self.host().web()
+        )));
+      }
+    }
+
+    let owner = if self.host().forge() == Forge::HuggingFace {
+      hugging_face::resolve_owner(self, &slug.owner, &slug.repo).await?
+    } else {
+      slug.owner.clone()
+    };
+
+    Ok((owner, slug.repo.clone()))
+  }
+
+  pub async fn repo(&self, owner: &str, repo: &str) -> Result<Repo, RepoIconsError> {
+    Repo::load_with_client(self, owner, repo).await
+  }
+
+  /// Load the images in the readme, or `None` if it has no images
+  pub async fn readme(&self, owner: &str, repo: &str) -> Result<Option<Readme>, RepoIconsError> {
+    Readme::load_with_client(self, owner, repo).await
+  }
+
+  pub fn token(&self) -> Option<String> {
+    match &self.0.token {
+      Token::Global => get_token(),
+      Token::Explicit(token) => token.clone(),
+    }
+  }
+
+  pub fn user_agent(&self) -> &str {
+    &self.0.user_agent
+  }
+
+  pub fn host(&self) -> &ForgeHost {
+    &self.0.host
+  }
+
+  /// The underlying HTTP client, without any GitHub credentials attached
+  pub fn http(&self) -> &Client {
+    &self.0.http
+  }
+
+  /// The remaining GitHub API budget, as of the last response
+  pub fn rate_limit(&self) -> Option<RateLimit> {
+    *self.0.rate_limit.lock().unwrap()
+  }
+
+  pub(crate) fn set_rate_limit(&self, rate_limit: RateLimit) {
+    *self.0.rate_limit.lock().unwrap() = Some(rate_limit);
+  }
+
+  pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+    &self.0.retry_policy
+  }
+
+  pub(crate) fn backend(&self) -> &dyn GithubBackend {
+    &*self.0.backend
+  }
+
+  pub(crate) fn caches(&self) -> &GithubCaches {
+    &self.0.caches
+  }
+
+  /// A GET request to the forge API, authenticated with the client's token
+  pub fn api_get(&self, path: &str) -> RequestBuilder {
+    self.get(self.host().api_url(path))
+  }
+
+  /// A GET request to a url on the forge, authenticated with the client's token
+  pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.authenticate(self.0.http.get(url))
+  }
+
+  /// A POST request to a url on the forge, authenticated with the client's token
+  pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.authenticate(self.0.http.post(url))
+  }
+
+  fn authenticate(&self, mut request: RequestBuilder) -> RequestBuilder {
+    if let Some(token) = self.token() {
+      request = request.header("Authorization", self.host().auth_header(&token));
+    }
+
+    request
+  }
+}
+
+#[derive(Default)]
+pub struct RepoIconsClientBuilder {
+  token: Option<Option<String>>,
+  http: Option<Client>,
+  user_agent: Option<String>,
+  host: Option<ForgeHost>,
+  proxy: Option<Url>,
+  retry_policy: Option<RetryPolicy>,
+  backend: Option<Arc<dyn GithubBackend>>,
+  cache: Option<Arc<dyn Cache>>,
+  cache_ttls: HashMap<CacheKind, Duration>,
+}
+
+impl RepoIconsClientBuilder {
+  /// The GitHub token to use, `None` to make unauthenticated requests.
+  /// Falls back to [`set_token`](crate::set_token) when it isn't set
+  pub fn token<T: ToString>(mut self, token: Option<T>) -> Self {
+    self.token = Some(token.map(|token| token.to_string()));
+    self
+  }
+
+  pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> Self {
+    self.user_agent = Some(user_agent.into());
+    self
+  }
+
+  /// The forge to load repos from, defaults to github.com
+  pub fn host(mut self, host: ForgeHost) -> Self {
+    self.host = Some(host);
+    self
+  }
+
+  /// Route all requests through a proxy. Not supported on wasm
+  pub fn proxy(mut self, proxy: Url) -> Self {
+    self.proxy = Some(proxy);
+    self
+  }
+
+  /// Use an existing HTTP client, instead of building one.
+  /// The user agent & proxy options are ignored when this is set
+  pub fn http_client(mut self, http: Client) -> Self {
+    self.http = Some(http);
+    self
+  }
+
+  pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+    self.retry_policy = Some(retry_policy);
+    self
+  }
+
+  /// Send requests through something other than the network, such as
+  /// [`FixtureBackend`](crate::FixtureBackend) in tests. Defaults to [`HttpBackend`]
+  pub fn backend<B: GithubBackend + 'static>(mut self, backend: B) -> Self {
+    self.backend = Some(Arc::new(backend));
+    self
+  }
+
+  /// Where to store API responses, in addition to the memory of the client.
+  /// Defaults to [`NoopCache`](crate::NoopCache)
+  pub fn cache<C: Cache + 'static>(mut self, cache: C) -> Self {
+    self.cache = Some(Arc::new(cache));
+    self
+  }
+
+  /// How long responses of a kind are cached for, see [`CacheKind::default_ttl`]
+  pub fn cache_ttl(mut self, kind: CacheKind, ttl: Duration) -> Self {
+    self.cache_ttls.insert(kind, ttl);
+    self
+  }
+
+  /// Fails if the user agent isn't a valid header, or the proxy isn't supported
+  pub fn build(self) -> Result<RepoIconsClient, RepoIconsError> {
+    let user_agent = self
+      .user_agent
+      .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string());
+
+    // proxied clients get their own pool
+    let http = match (self.http, self.proxy) {
+      (Some(http), _) => http,
+      (None, Some(proxy)) => http_client(&user_agent, Some(proxy))?,
+      (None, None) => {
+        let mut clients = HTTP_CLIENTS.lock().unwrap();
+        match clients.get(&user_agent) {
+          Some(http) => http.clone(),
+          None => {
+            let http = http_client(&user_agent, None)?;
+            clients.insert(user_agent.clone(), http.clone());
+            http
+          }
+        }
+      }
+    };
+
+    let host = self.host.unwrap_or_default();
+    let cache = self.cache.unwrap_or_else(|| Arc::new(NoopCache));
+    let caches = GithubCaches::new(&host, cache, &self.cache_ttls);
+
+    Ok(RepoIconsClient(Arc::new(ClientInner {
+      token: self.token.map(Token::Explicit).unwrap_or(Token::Global),
+      http,
+      user_agent,
+      host,
+      retry_policy: self.retry_policy.unwrap_or_default(),
+      backend: self.backend.unwrap_or_else(|| Arc::new(HttpBackend)),
+      rate_limit: Mutex::new(None),
+      caches,
+    })))
+  }
+}
+
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn http_client(user_agent: &str, proxy: Option<Url>) -> Result<Client, RepoIconsError> {
+  let mut headers = HeaderMap::new();
+  headers.insert(
+    USER_AGENT,
+    HeaderValue::from_str(user_agent)
+      .map_err(|_| RepoIconsError::other(format!("invalid user agent {:?}", user_agent)))?,
+  );
+
+  #[allow(unused_mut)]
+  let mut builder = Client::builder().default_headers(headers);
+
+  #[cfg(not(target_arch = "wasm32"))]
+  if let Some(proxy) = proxy {
+    builder = builder.proxy(reqwest::Proxy::all(proxy).map_err(RepoIconsError::other)?);
+  }
+
+  builder.build().map_err(RepoIconsError::other)
+}
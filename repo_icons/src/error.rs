@@ -0,0 +1,194 @@
+use instant::Duration;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum RepoIconsError {
+  NotFound {
+    url: Option<Url>,
+  },
+  RateLimited {
+    url: Option<Url>,
+    /// unix timestamp (in seconds) of when the rate limit resets
+    reset: Option<u64>,
+  },
+  Unauthorized {
+    url: Option<Url>,
+  },
+  Timeout {
+    #[serde(with = "duration_millis")]
+    after: Duration,
+  },
+  Network {
+    url: Option<Url>,
+    message: String,
+  },
+  InvalidImage {
+    url: Option<Url>,
+    message: String,
+  },
+  Http {
+    url: Option<Url>,
+    status: u16,
+    message: String,
+  },
+  NoIconsFound,
+  Other {
+    message: String,
+  },
+}
+
+impl RepoIconsError {
+  pub fn other<E: ToString>(error: E) -> Self {
+    RepoIconsError::Other {
+      message: error.to_string(),
+    }
+  }
+
+  pub fn url(&self) -> Option<&Url> {
+    match self {
+      RepoIconsError::NotFound { url }
+      | RepoIconsError::RateLimited { url, .. }
+      | RepoIconsError::Unauthorized { url }
+      | RepoIconsError::Network { url, .. }
+      | RepoIconsError::InvalidImage { url, .. }
+      | RepoIconsError::Http { url, .. } => url.as_ref(),
+      RepoIconsError::Timeout { .. }
+      | RepoIconsError::NoIconsFound
+      | RepoIconsError::Other { .. } => None,
+    }
+  }
+
+  /// Whether the same request could succeed later, so that a result missing it
+  /// is incomplete. Not found repos & sources that timed out aren't, as they're expected
+  pub fn is_transient(&self) -> bool {
+    match self {
+      RepoIconsError::RateLimited { .. } | RepoIconsError::Network { .. } => true,
+      RepoIconsError::Http { status, .. } => *status >= 500,
+      _ => false,
+    }
+  }
+
+  /// Turn an unsuccessful response into an error, based on its status & rate limit headers
+  pub async fn from_response(url: &Url, res: Response) -> Self {
+    let url = Some(url.clone());
+    let status = res.status();
+    let header = |name: &str| {
+      res
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+    };
+
+    let header = |name: &str| header(&format!("x-{}", name)).or_else(|| header(name));
+    let rate_limit_exhausted = header("ratelimit-remaining").as_deref() == Some("0");
+    let reset = header("ratelimit-reset").and_then(|reset| reset.parse().ok());
+
+    let message = res.text().await.unwrap_or_default();
+
+    match status {
+      StatusCode::NOT_FOUND => RepoIconsError::NotFound { url },
+      StatusCode::UNAUTHORIZED => RepoIconsError::Unauthorized { url },
+      StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        if rate_limit_exhausted || message.contains("rate limit") =>
+      {
+        RepoIconsError::RateLimited { url, reset }
+      }
+      status => RepoIconsError::Http {
+        url,
+        status: status.as_u16(),
+        message,
+      },
+    }
+  }
+}
+
+impl Display for RepoIconsError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      RepoIconsError::NotFound { .. } => write!(f, "not found"),
+      RepoIconsError::RateLimited { reset, .. } => match reset {
+        Some(reset) => write!(f, "rate limited until {}", reset),
+        None => write!(f, "rate limited"),
+      },
+      RepoIconsError::Unauthorized { .. } => write!(f, "unauthorized"),
+      RepoIconsError::Timeout { after } => write!(f, "timed out after {:?}", after),
+      RepoIconsError::Network { message, .. } => write!(f, "network error: {}", message),
+      RepoIconsError::InvalidImage { message, .. } => write!(f, "invalid image: {}", message),
+      RepoIconsError::Http {
+        status, message, ..
+      } => write!(f, "{}: {}", status, message),
+      RepoIconsError::NoIconsFound => write!(f, "No icons found for repo"),
+      RepoIconsError::Other { message } => write!(f, "{}", message),
+    }?;
+
+    if let Some(url) = self.url() {
+      write!(f, " ({})", url)?;
+    }
+
+    Ok(())
+  }
+}
+
+impl std::error::Error for RepoIconsError {}
+
+impl From<reqwest::Error> for RepoIconsError {
+  fn from(error: reqwest::Error) -> Self {
+    RepoIconsError::Network {
+      url: error.url().cloned(),
+      message: error.to_string(),
+    }
+  }
+}
+
+impl From<url::ParseError> for RepoIconsError {
+  fn from(error: url::ParseError) -> Self {
+    RepoIconsError::other(error)
+  }
+}
+
+/// An error that occurred while loading one of the sources
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepoIconsDiagnostic {
+  /// The name of the [`IconSource`](crate::IconSource) that failed
+  pub source: String,
+  pub url: Option<Url>,
+  /// How long the source ran for before it failed
+  #[serde(with = "duration_millis")]
+  pub duration: Duration,
+  pub error: RepoIconsError,
+}
+
+impl RepoIconsDiagnostic {
+  pub fn new(source: &str, duration: Duration, error: RepoIconsError) -> Self {
+    Self {
+      source: source.to_string(),
+      url: error.url().cloned(),
+      duration,
+      error,
+    }
+  }
+}
+
+impl Display for RepoIconsDiagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(f, "{}: {} in {:?}", self.source, self.error, self.duration)
+  }
+}
+
+mod duration_millis {
+  use instant::Duration;
+  use serde::{Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+  }
+}
@@ -0,0 +1,84 @@
+use crate::RepoIconRank;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+/// A heuristic that matched a candidate, and the weight it added
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeightRule {
+  pub rule: String,
+  pub weight: u8,
+}
+
+/// The total weight of a candidate, broken down by the rules that matched it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+  pub weight: u8,
+  pub rules: Vec<WeightRule>,
+}
+
+impl Score {
+  pub(crate) fn add(&mut self, rule: &str, weight: u8) {
+    self.weight += weight;
+    self.rules.push(WeightRule {
+      rule: rule.to_string(),
+      weight,
+    });
+  }
+}
+
+/// A file / image that a source considered, but didn't pick.
+/// Listed once for the whole load, see [`RepoIconsResult::rejected`](crate::RepoIconsResult::rejected)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RejectedCandidate {
+  /// The repo path or url of the candidate
+  pub candidate: String,
+  pub score: Score,
+  pub reason: String,
+}
+
+impl Display for RejectedCandidate {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(
+      f,
+      "rejected {} (weight {}): {}",
+      self.candidate, self.score.weight, self.reason
+    )
+  }
+}
+
+/// Why an icon ended up at its position, see [`RepoIconsOptions::explain`](crate::RepoIconsOptions::explain)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Explanation {
+  /// The position of the icon in the ranked list
+  pub position: usize,
+  /// Icons are ordered by the rank of their kind first,
+  /// and then by their format & size
+  pub rank: RepoIconRank,
+  /// How the source scored the icon, if it uses weights to pick between candidates
+  pub score: Option<Score>,
+}
+
+impl Explanation {
+  pub fn new(rank: RepoIconRank) -> Self {
+    Self {
+      position: 0,
+      rank,
+      score: None,
+    }
+  }
+}
+
+impl Display for Explanation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    writeln!(f, "  #{} ranked as {:?}", self.position, self.rank)?;
+
+    if let Some(score) = &self.score {
+      writeln!(f, "  weight {}", score.weight)?;
+      for rule in &score.rules {
+        writeln!(f, "    +{} {}", rule.weight, rule.rule)?;
+      }
+    }
+
+    Ok(())
+  }
+}
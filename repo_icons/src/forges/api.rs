@@ -0,0 +1,55 @@
+use crate::{github_api::send, RepoIconsClient, RepoIconsError};
+use instant::Instant;
+use serde::de::DeserializeOwned;
+
+#[derive(Deserialize)]
+struct Branch {
+  commit: Commit,
+}
+
+/// GitLab names the sha of commits `id`, Gitea `sha` & Bitbucket `hash`
+#[derive(Deserialize)]
+struct Commit {
+  #[serde(alias = "sha", alias = "hash")]
+  id: String,
+}
+
+/// GET a path on the forge API, parsing the response as JSON
+pub async fn get_json<T: DeserializeOwned>(
+  client: &RepoIconsClient,
+  path: &str,
+) -> Result<T, RepoIconsError> {
+  let url = client.host().api_url(path);
+  let start = Instant::now();
+
+  let res = async {
+    send(client, &url, || client.api_get(path))
+      .await?
+      .json::<T>()
+      .await
+      .map_err(RepoIconsError::from)
+  }
+  .await;
+
+  info!("{}: {:?}", url, start.elapsed());
+
+  res
+}
+
+/// The commit sha a branch points to, GitLab & Gitea return branches in the same shape
+pub async fn get_branch_commit(
+  client: &RepoIconsClient,
+  path: &str,
+) -> Result<String, RepoIconsError> {
+  let branch = get_json::<Branch>(client, path).await?;
+  Ok(branch.commit.id)
+}
+
+/// The sha of a commit, looked up by any git ref
+pub async fn get_commit_sha(
+  client: &RepoIconsClient,
+  path: &str,
+) -> Result<String, RepoIconsError> {
+  let commit = get_json::<Commit>(client, path).await?;
+  Ok(commit.id)
+}
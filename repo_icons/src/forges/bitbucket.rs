@@ -0,0 +1,167 @@
+use super::{encode, get_commit_sha, get_json};
+use crate::{
+  github_api::{parse_homepage, File, FileType, Repo, User},
+  RepoIconsClient, RepoIconsError,
+};
+use url::Url;
+
+/// How many directories deep to list files,
+/// deeper listings are slow on large repos
+const MAX_DEPTH: u32 = 5;
+
+/// Listings are paginated, so give up on huge repos rather than
+/// making hundreds of requests
+const MAX_SRC_PAGES: u32 = 20;
+
+#[derive(Deserialize)]
+struct Repository {
+  slug: String,
+  owner: Owner,
+  workspace: Workspace,
+  /// missing for empty repos
+  mainbranch: Option<Branch>,
+  is_private: bool,
+  website: Option<String>,
+  links: Links,
+}
+
+#[derive(Deserialize)]
+struct Owner {
+  /// `user` or `team`
+  r#type: String,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+  slug: String,
+  links: Links,
+}
+
+#[derive(Deserialize)]
+struct Branch {
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct Links {
+  avatar: Link,
+}
+
+#[derive(Deserialize)]
+struct Link {
+  href: String,
+}
+
+#[derive(Deserialize)]
+struct Src {
+  values: Vec<SrcEntry>,
+  next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SrcEntry {
+  path: String,
+  r#type: String,
+}
+
+pub async fn get_repo(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<Repo, RepoIconsError> {
+  let repository =
+    get_json::<Repository>(client, &format!("repositories/{}/{}", owner, repo)).await?;
+
+  Ok(Repo {
+    owner: User {
+      login: repository.workspace.slug,
+      r#type: if repository.owner.r#type == "team" {
+        "Organization".to_string()
+      } else {
+        "User".to_string()
+      },
+      avatar_url: repository.workspace.links.avatar.href.parse().ok(),
+    },
+    name: repository.slug,
+    default_branch: repository
+      .mainbranch
+      .map(|branch| branch.name)
+      .unwrap_or_default(),
+    private: repository.is_private,
+    homepage: repository.website.as_deref().and_then(parse_homepage),
+    // every repo has a generated avatar, which is marked with ?ts=default
+    avatar: repository
+      .links
+      .avatar
+      .href
+      .parse()
+      .ok()
+      .filter(|avatar: &Url| {
+        !avatar
+          .query_pairs()
+          .any(|(key, value)| key == "ts" && value == "default")
+      }),
+  })
+}
+
+pub async fn get_repo_files(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+) -> Result<(String, Vec<File>), RepoIconsError> {
+  let git_ref = match git_ref {
+    Some(git_ref) => git_ref.to_string(),
+    None => {
+      Repo::load_with_client(client, owner, repo)
+        .await?
+        .default_branch
+    }
+  };
+
+  // commits can be looked up by any branch or tag
+  let sha = get_commit_sha(
+    client,
+    &format!(
+      "repositories/{}/{}/commit/{}",
+      owner,
+      repo,
+      encode(&git_ref)
+    ),
+  )
+  .await?;
+
+  let mut files = Vec::new();
+  let mut page = Some(format!(
+    "repositories/{}/{}/src/{}/?max_depth={}&pagelen=100",
+    owner, repo, sha, MAX_DEPTH
+  ));
+
+  for _ in 0..MAX_SRC_PAGES {
+    // the next page is an absolute url, which joining onto the api url keeps as is
+    let path = match page.take() {
+      Some(path) => path,
+      None => break,
+    };
+
+    let src = get_json::<Src>(client, &path).await?;
+    page = src.next;
+
+    files.extend(src.values.into_iter().filter_map(|entry| {
+      let r#type = match &entry.r#type[..] {
+        "commit_file" => FileType::Blob,
+        "commit_directory" => FileType::Tree,
+        _ => return None,
+      };
+
+      Some(File {
+        // listings only have the commit hash, so frameworks can't be detected by blob sha
+        sha: None,
+        path: entry.path,
+        r#type,
+      })
+    }));
+  }
+
+  Ok((sha, files))
+}
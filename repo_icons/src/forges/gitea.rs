@@ -0,0 +1,120 @@
+use super::{encode, get_branch_commit, get_commit_sha, get_json};
+use crate::{
+  github_api::{parse_homepage, File, Repo, User},
+  RepoIconsClient, RepoIconsError,
+};
+use futures::join;
+use serde::de::IgnoredAny;
+
+/// Trees are paginated, so give up on huge repos rather than
+/// making hundreds of requests
+const MAX_TREE_PAGES: u32 = 10;
+
+#[derive(Deserialize)]
+struct Repository {
+  owner: Owner,
+  name: String,
+  default_branch: String,
+  private: bool,
+  /// empty when not set
+  #[serde(default)]
+  website: String,
+  /// empty when not set
+  #[serde(default)]
+  avatar_url: String,
+}
+
+#[derive(Deserialize)]
+struct Owner {
+  login: String,
+  avatar_url: String,
+}
+
+#[derive(Deserialize)]
+struct Tree {
+  tree: Vec<File>,
+  truncated: bool,
+}
+
+pub async fn get_repo(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<Repo, RepoIconsError> {
+  // users & organizations look the same on repos,
+  // so check whether an organization exists instead
+  let (repository, organization) = join!(
+    get_json::<Repository>(client, &format!("repos/{}/{}", owner, repo)),
+    get_json::<IgnoredAny>(client, &format!("orgs/{}", owner))
+  );
+  let repository = repository?;
+
+  Ok(Repo {
+    owner: User {
+      login: repository.owner.login,
+      r#type: if organization.is_ok() {
+        "Organization".to_string()
+      } else {
+        "User".to_string()
+      },
+      avatar_url: repository.owner.avatar_url.parse().ok(),
+    },
+    name: repository.name,
+    default_branch: repository.default_branch,
+    private: repository.private,
+    homepage: parse_homepage(&repository.website),
+    avatar: repository.avatar_url.parse().ok(),
+  })
+}
+
+pub async fn get_repo_files(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+) -> Result<(String, Vec<File>), RepoIconsError> {
+  let sha = match git_ref {
+    Some(git_ref) => {
+      get_commit_sha(
+        client,
+        &format!("repos/{}/{}/git/commits/{}", owner, repo, encode(git_ref)),
+      )
+      .await?
+    }
+    None => {
+      let repository = Repo::load_with_client(client, owner, repo).await?;
+
+      get_branch_commit(
+        client,
+        &format!(
+          "repos/{}/{}/branches/{}",
+          owner,
+          repo,
+          encode(&repository.default_branch)
+        ),
+      )
+      .await?
+    }
+  };
+
+  let mut files = Vec::new();
+
+  for page in 1..=MAX_TREE_PAGES {
+    let tree = get_json::<Tree>(
+      client,
+      &format!(
+        "repos/{}/{}/git/trees/{}?recursive=true&per_page=1000&page={}",
+        owner, repo, sha, page
+      ),
+    )
+    .await?;
+
+    files.extend(tree.tree);
+
+    if !tree.truncated {
+      break;
+    }
+  }
+
+  Ok((sha, files))
+}
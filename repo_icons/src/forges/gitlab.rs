@@ -0,0 +1,133 @@
+use super::{encode, get_branch_commit, get_commit_sha, get_json};
+use crate::{
+  github_api::{header, send, File, Repo, User},
+  RepoIconsClient, RepoIconsError,
+};
+use instant::Instant;
+use url::Url;
+
+/// Trees are paginated, so give up on huge repos rather than
+/// making hundreds of requests
+const MAX_TREE_PAGES: u32 = 20;
+
+#[derive(Deserialize)]
+struct Project {
+  path: String,
+  namespace: Namespace,
+  default_branch: Option<String>,
+  /// only returned to authenticated users
+  visibility: Option<String>,
+  avatar_url: Option<Url>,
+  description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Namespace {
+  full_path: String,
+  kind: String,
+  /// relative to the website for groups
+  avatar_url: Option<String>,
+}
+
+fn project_path(owner: &str, repo: &str) -> String {
+  format!("projects/{}", encode(&format!("{}/{}", owner, repo)))
+}
+
+pub async fn get_repo(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<Repo, RepoIconsError> {
+  let project = get_json::<Project>(client, &project_path(owner, repo)).await?;
+
+  let homepage = project
+    .description
+    .as_deref()
+    .and_then(|description| {
+      regex!(r#"https?://[^\s)\]>"']+"#)
+        .find(description)
+        .unwrap()
+    })
+    .and_then(|link| Url::parse(link.as_str()).ok());
+
+  Ok(Repo {
+    owner: User {
+      login: project.namespace.full_path,
+      r#type: if project.namespace.kind == "group" {
+        "Organization".to_string()
+      } else {
+        "User".to_string()
+      },
+      avatar_url: project
+        .namespace
+        .avatar_url
+        .and_then(|avatar_url| client.host().web().join(&avatar_url).ok()),
+    },
+    name: project.path,
+    // empty projects don't have a default branch
+    default_branch: project.default_branch.unwrap_or_default(),
+    private: project
+      .visibility
+      .map(|visibility| visibility != "public")
+      .unwrap_or(false),
+    homepage,
+    avatar: project.avatar_url,
+  })
+}
+
+pub async fn get_repo_files(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+) -> Result<(String, Vec<File>), RepoIconsError> {
+  let project_path = project_path(owner, repo);
+
+  let sha = match git_ref {
+    Some(git_ref) => {
+      get_commit_sha(
+        client,
+        &format!("{}/repository/commits/{}", project_path, encode(git_ref)),
+      )
+      .await?
+    }
+    None => {
+      let project = Repo::load_with_client(client, owner, repo).await?;
+
+      get_branch_commit(
+        client,
+        &format!(
+          "{}/repository/branches/{}",
+          project_path,
+          encode(&project.default_branch)
+        ),
+      )
+      .await?
+    }
+  };
+
+  let mut files = Vec::new();
+  let mut page = Some("1".to_string());
+
+  for _ in 0..MAX_TREE_PAGES {
+    let current = match page.take() {
+      Some(current) => current,
+      None => break,
+    };
+
+    let path = format!(
+      "{}/repository/tree?recursive=true&per_page=100&ref={}&page={}",
+      project_path, sha, current
+    );
+    let url = client.host().api_url(&path);
+    let start = Instant::now();
+
+    let res = send(client, &url, || client.api_get(&path)).await?;
+    page = header(&res, "x-next-page").filter(|next| !next.is_empty());
+    files.extend(res.json::<Vec<File>>().await?);
+
+    info!("{}: {:?}", url, start.elapsed());
+  }
+
+  Ok((sha, files))
+}
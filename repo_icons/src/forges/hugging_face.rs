@@ -0,0 +1,252 @@
+use super::{encode, get_json};
+use crate::{
+  github_api::{File, FileType, Repo, User},
+  RepoIconsClient, RepoIconsError,
+};
+use futures::join;
+use url::Url;
+
+/// Spaces & datasets live under a prefix on the website, which is kept
+/// as part of the owner (`spaces/gradio`) so that paths can be built from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RepoType {
+  Model,
+  Space,
+  Dataset,
+}
+
+impl RepoType {
+  fn split(owner: &str) -> (Self, &str) {
+    if let Some(owner) = owner.strip_prefix("spaces/") {
+      (RepoType::Space, owner)
+    } else if let Some(owner) = owner.strip_prefix("datasets/") {
+      (RepoType::Dataset, owner)
+    } else {
+      (RepoType::Model, owner)
+    }
+  }
+
+  fn prefix(&self) -> &'static str {
+    match self {
+      RepoType::Model => "",
+      RepoType::Space => "spaces/",
+      RepoType::Dataset => "datasets/",
+    }
+  }
+
+  fn api_path(&self, owner: &str, repo: &str) -> String {
+    let kind = match self {
+      RepoType::Model => "models",
+      RepoType::Space => "spaces",
+      RepoType::Dataset => "datasets",
+    };
+
+    format!("{}/{}/{}", kind, owner, repo)
+  }
+}
+
+#[derive(Deserialize)]
+struct Info {
+  id: String,
+  /// missing for empty repos
+  sha: Option<String>,
+  #[serde(default)]
+  private: bool,
+  #[serde(default)]
+  siblings: Vec<Sibling>,
+  #[serde(rename = "cardData")]
+  card_data: Option<CardData>,
+  #[serde(rename = "authorData")]
+  author_data: Option<AuthorData>,
+}
+
+/// The user or organization that owns the repo
+#[derive(Deserialize)]
+struct AuthorData {
+  /// `org` or `user`
+  r#type: String,
+  #[serde(rename = "avatarUrl")]
+  avatar_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Sibling {
+  rfilename: String,
+}
+
+/// The front matter of the readme
+#[derive(Deserialize)]
+struct CardData {
+  emoji: Option<String>,
+  #[serde(rename = "colorFrom")]
+  color_from: Option<String>,
+  #[serde(rename = "colorTo")]
+  color_to: Option<String>,
+}
+
+async fn get_info(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+) -> Result<Info, RepoIconsError> {
+  let (repo_type, owner) = RepoType::split(owner);
+  let mut path = repo_type.api_path(owner, repo);
+  if let Some(git_ref) = git_ref {
+    path = format!("{}/revision/{}", path, encode(git_ref));
+  }
+
+  get_json::<Info>(client, &path).await
+}
+
+/// Find out whether `owner/repo` is a model, space or dataset,
+/// returning the owner with the prefix of its type
+pub async fn resolve_owner(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<String, RepoIconsError> {
+  if RepoType::split(owner).0 != RepoType::Model {
+    return Ok(owner.to_string());
+  }
+
+  let (model, space, dataset) = join!(
+    get_json::<Info>(client, &RepoType::Model.api_path(owner, repo)),
+    get_json::<Info>(client, &RepoType::Space.api_path(owner, repo)),
+    get_json::<Info>(client, &RepoType::Dataset.api_path(owner, repo))
+  );
+
+  // models take priority, as they're what huggingface.co/owner/repo links to
+  for (repo_type, info) in [
+    (RepoType::Model, model),
+    (RepoType::Space, space),
+    (RepoType::Dataset, dataset),
+  ] {
+    match info {
+      Ok(_) => return Ok(format!("{}{}", repo_type.prefix(), owner)),
+      Err(RepoIconsError::NotFound { .. }) => {}
+      Err(err) => return Err(err),
+    }
+  }
+
+  Err(RepoIconsError::NotFound { url: None })
+}
+
+pub async fn get_repo(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<Repo, RepoIconsError> {
+  let name = RepoType::split(owner).1;
+  let info = get_info(client, owner, repo, None).await?;
+
+  let is_org = matches!(&info.author_data, Some(author) if author.r#type == "org");
+  let avatar_url = info
+    .author_data
+    .and_then(|author| author.avatar_url)
+    // avatars of organizations are relative to the website
+    .and_then(|avatar_url| client.host().web().join(&avatar_url).ok());
+
+  Ok(Repo {
+    owner: User {
+      login: name.to_string(),
+      r#type: if is_org {
+        "Organization".to_string()
+      } else {
+        "User".to_string()
+      },
+      avatar_url,
+    },
+    name: info.id.rsplit('/').next().unwrap_or(repo).to_string(),
+    // the Hub doesn't name its default branch, so the commit it resolves to is used
+    default_branch: info.sha.unwrap_or_default(),
+    private: info.private,
+    homepage: None,
+    avatar: None,
+  })
+}
+
+pub async fn get_repo_files(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+) -> Result<(String, Vec<File>), RepoIconsError> {
+  let info = get_info(client, owner, repo, git_ref).await?;
+  let sha = info.sha.ok_or(RepoIconsError::NotFound { url: None })?;
+
+  let files = info
+    .siblings
+    .into_iter()
+    .map(|sibling| File {
+      // listings don't include blob shas
+      sha: None,
+      path: sibling.rfilename,
+      r#type: FileType::Blob,
+    })
+    .collect();
+
+  Ok((sha, files))
+}
+
+/// An icon for a Space, drawn from the emoji & gradient that the Hub shows on its card.
+/// `None` for models, datasets & spaces without an emoji
+pub async fn get_space_icon(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<Option<Url>, RepoIconsError> {
+  if RepoType::split(owner).0 != RepoType::Space {
+    return Ok(None);
+  }
+
+  let card_data = match get_info(client, owner, repo, None).await?.card_data {
+    Some(card_data) => card_data,
+    None => return Ok(None),
+  };
+
+  let emoji = match card_data.emoji.filter(|emoji| !emoji.trim().is_empty()) {
+    Some(emoji) => emoji,
+    None => return Ok(None),
+  };
+
+  let svg = format!(
+    concat!(
+      r#"<svg xmlns="http://www.w3.org/2000/svg" width="128" height="128" viewBox="0 0 128 128">"#,
+      r#"<defs><linearGradient id="gradient" x1="0" y1="0" x2="1" y2="1">"#,
+      r#"<stop offset="0" stop-color="{}"/><stop offset="1" stop-color="{}"/>"#,
+      r#"</linearGradient></defs>"#,
+      r#"<rect width="128" height="128" rx="24" fill="url(#gradient)"/>"#,
+      r#"<text x="64" y="68" font-size="72" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+      r#"</svg>"#
+    ),
+    color(card_data.color_from.as_deref()),
+    color(card_data.color_to.as_deref()),
+    escape(emoji.trim())
+  );
+
+  Ok(Some(
+    Url::parse(&format!("data:image/svg+xml,{}", encode(&svg))).unwrap(),
+  ))
+}
+
+/// Spaces pick from a fixed set of tailwind colors
+fn color(name: Option<&str>) -> &'static str {
+  match name.unwrap_or_default() {
+    "red" => "#ef4444",
+    "yellow" => "#eab308",
+    "green" => "#22c55e",
+    "blue" => "#3b82f6",
+    "indigo" => "#6366f1",
+    "purple" => "#a855f7",
+    "pink" => "#ec4899",
+    _ => "#6b7280",
+  }
+}
+
+fn escape(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
@@ -0,0 +1,372 @@
+mod api;
+pub(crate) mod bitbucket;
+pub(crate) mod gitea;
+pub(crate) mod gitlab;
+pub(crate) mod hugging_face;
+mod readme;
+
+pub(crate) use api::*;
+pub(crate) use readme::*;
+
+use crate::RepoIconsError;
+use std::{
+  fmt::{self, Display},
+  str::FromStr,
+};
+use url::{form_urlencoded, Url};
+
+/// The software a [`ForgeHost`] runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Forge {
+  Github,
+  Gitlab,
+  /// Gitea, or one of its forks such as Forgejo (which runs Codeberg)
+  Gitea,
+  /// Bitbucket Cloud
+  Bitbucket,
+  /// The Hugging Face Hub, where owners of spaces & datasets
+  /// are prefixed with `spaces/` & `datasets/`
+  HuggingFace,
+}
+
+impl Forge {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Forge::Github => "github",
+      Forge::Gitlab => "gitlab",
+      Forge::Gitea => "gitea",
+      Forge::Bitbucket => "bitbucket",
+      Forge::HuggingFace => "huggingface",
+    }
+  }
+}
+
+impl Display for Forge {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+impl FromStr for Forge {
+  type Err = RepoIconsError;
+
+  fn from_str(name: &str) -> Result<Self, Self::Err> {
+    Ok(match &name.to_lowercase()[..] {
+      "github" => Forge::Github,
+      "gitlab" => Forge::Gitlab,
+      "gitea" | "forgejo" | "codeberg" => Forge::Gitea,
+      "bitbucket" => Forge::Bitbucket,
+      "huggingface" | "hf" => Forge::HuggingFace,
+      _ => return Err(RepoIconsError::other(format!("unknown forge {}", name))),
+    })
+  }
+}
+
+/// Where a forge serves its website, API & raw files from.
+/// Defaults to github.com
+///
+/// ```
+/// let client = RepoIconsClient::builder()
+///   .host(ForgeHost::github_enterprise("https://github.example.com".parse()?))
+///   .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForgeHost {
+  forge: Forge,
+  web: Url,
+  api: Url,
+  raw: Url,
+}
+
+impl Default for ForgeHost {
+  fn default() -> Self {
+    Self::new(
+      Forge::Github,
+      Url::parse("https://github.com/").unwrap(),
+      Url::parse("https://api.github.com/").unwrap(),
+      Url::parse("https://raw.githubusercontent.com/").unwrap(),
+    )
+  }
+}
+
+impl ForgeHost {
+  pub fn new(forge: Forge, web: Url, api: Url, raw: Url) -> Self {
+    Self {
+      forge,
+      web: with_trailing_slash(web),
+      api: with_trailing_slash(api),
+      raw: with_trailing_slash(raw),
+    }
+  }
+
+  pub fn github() -> Self {
+    Self::default()
+  }
+
+  /// A GitHub Enterprise Server instance, which serves the API
+  /// from `/api/v3/` and raw files from `/raw/`
+  pub fn github_enterprise(web: Url) -> Self {
+    let web = with_trailing_slash(web);
+    let api = web.join("api/v3/").unwrap();
+    let raw = web.join("raw/").unwrap();
+
+    Self::new(Forge::Github, web, api, raw)
+  }
+
+  /// gitlab.com, or a self-hosted GitLab instance
+  pub fn gitlab(web: Url) -> Self {
+    let web = with_trailing_slash(web);
+    let api = web.join("api/v4/").unwrap();
+
+    Self::new(Forge::Gitlab, web.clone(), api, web)
+  }
+
+  /// A Gitea or Forgejo instance, such as Codeberg
+  pub fn gitea(web: Url) -> Self {
+    let web = with_trailing_slash(web);
+    let api = web.join("api/v1/").unwrap();
+
+    Self::new(Forge::Gitea, web.clone(), api, web)
+  }
+
+  /// bitbucket.org, Bitbucket Data Center has a different API and isn't supported
+  pub fn bitbucket() -> Self {
+    Self::new(
+      Forge::Bitbucket,
+      Url::parse("https://bitbucket.org/").unwrap(),
+      Url::parse("https://api.bitbucket.org/2.0/").unwrap(),
+      Url::parse("https://bitbucket.org/").unwrap(),
+    )
+  }
+
+  /// huggingface.co
+  pub fn hugging_face() -> Self {
+    let web = Url::parse("https://huggingface.co/").unwrap();
+    let api = web.join("api/").unwrap();
+
+    Self::new(Forge::HuggingFace, web.clone(), api, web)
+  }
+
+  /// The public instance of the forge, such as gitlab.com.
+  /// Codeberg is used for Gitea
+  pub fn public(forge: Forge) -> Self {
+    match forge {
+      Forge::Github => Self::github(),
+      Forge::Gitlab => Self::gitlab(Url::parse("https://gitlab.com/").unwrap()),
+      Forge::Gitea => Self::gitea(Url::parse("https://codeberg.org/").unwrap()),
+      Forge::Bitbucket => Self::bitbucket(),
+      Forge::HuggingFace => Self::hugging_face(),
+    }
+  }
+
+  /// A host running the given forge, at the given website url
+  pub fn with_forge(forge: Forge, web: Url) -> Self {
+    match forge {
+      Forge::Github if web.host_str() == Some("github.com") => Self::github(),
+      Forge::Github => Self::github_enterprise(web),
+      Forge::Gitlab => Self::gitlab(web),
+      Forge::Gitea => Self::gitea(web),
+      Forge::Bitbucket => Self::bitbucket(),
+      Forge::HuggingFace => Self::hugging_face(),
+    }
+  }
+
+  /// The host of a well known public forge, such as gitlab.com
+  pub fn detect(web: &Url) -> Option<Self> {
+    Some(match web.host_str()? {
+      "github.com" => Self::public(Forge::Github),
+      "gitlab.com" => Self::public(Forge::Gitlab),
+      "codeberg.org" => Self::public(Forge::Gitea),
+      "gitea.com" => Self::gitea(web.join("/").unwrap()),
+      "bitbucket.org" => Self::bitbucket(),
+      "huggingface.co" => Self::hugging_face(),
+      _ => return None,
+    })
+  }
+
+  pub fn forge(&self) -> Forge {
+    self.forge
+  }
+
+  pub fn web(&self) -> &Url {
+    &self.web
+  }
+
+  pub fn api(&self) -> &Url {
+    &self.api
+  }
+
+  pub fn raw(&self) -> &Url {
+    &self.raw
+  }
+
+  pub fn is_github_com(&self) -> bool {
+    *self == Self::default()
+  }
+
+  pub fn api_url(&self, path: &str) -> Url {
+    self.api.join(path).unwrap()
+  }
+
+  /// The GitHub GraphQL API, which Enterprise Servers serve from `/api/graphql`
+  pub fn graphql_url(&self) -> Url {
+    if self.is_github_com() {
+      self.api_url("graphql")
+    } else {
+      self.api_url("../graphql")
+    }
+  }
+
+  pub fn web_url(&self, path: &str) -> Result<Url, url::ParseError> {
+    self.web.join(path)
+  }
+
+  /// Publicly accessible url of a file in the repo, at the given branch or commit
+  pub fn raw_url(&self, repo: &str, commitish: &str, path: &str) -> Url {
+    let path = match self.forge {
+      Forge::Github => format!("{}/{}/{}", repo, commitish, path),
+      Forge::Gitlab => format!("{}/-/raw/{}/{}", repo, commitish, path),
+      Forge::Gitea | Forge::Bitbucket => format!("{}/raw/{}/{}", repo, commitish, path),
+      Forge::HuggingFace => format!("{}/resolve/{}/{}", repo, commitish, path),
+    };
+
+    self.raw.join(&path).unwrap()
+  }
+
+  /// Url of a file in the repo that can be fetched with the
+  /// [`auth_header`](Self::auth_header), even if the repo is private
+  pub fn file_url(&self, repo: &str, commitish: &str, path: &str) -> Url {
+    match self.forge {
+      Forge::Github | Forge::HuggingFace => self.raw_url(repo, commitish, path),
+      Forge::Gitlab => self.api_url(&format!(
+        "projects/{}/repository/files/{}/raw?ref={}",
+        encode(repo),
+        encode(path),
+        encode(commitish)
+      )),
+      Forge::Gitea => self.api_url(&format!(
+        "repos/{}/raw/{}?ref={}",
+        repo,
+        encode_path(path),
+        encode(commitish)
+      )),
+      Forge::Bitbucket => self.api_url(&format!(
+        "repositories/{}/src/{}/{}",
+        repo,
+        encode(commitish),
+        encode_path(path)
+      )),
+    }
+  }
+
+  /// Url that relative links in the readme are resolved against
+  pub fn readme_base_url(&self, repo: &str, commitish: &str) -> Url {
+    let path = match self.forge {
+      Forge::Github => format!("{}/raw/{}/", repo, commitish),
+      Forge::Gitlab => format!("{}/-/raw/{}/", repo, commitish),
+      Forge::Gitea | Forge::Bitbucket => format!("{}/raw/{}/", repo, commitish),
+      Forge::HuggingFace => format!("{}/resolve/{}/", repo, commitish),
+    };
+
+    self.web.join(&path).unwrap()
+  }
+
+  /// The avatar of a GitHub user or organization
+  pub fn avatar_url(&self, user: &str) -> Url {
+    self.web.join(&format!("{}.png", user)).unwrap()
+  }
+
+  /// Value of the `Authorization` header for requests made with the token
+  pub fn auth_header(&self, token: &str) -> String {
+    match self.forge {
+      Forge::Gitea => format!("token {}", token),
+      _ => format!("Bearer {}", token),
+    }
+  }
+
+  pub fn is_api_url(&self, url: &Url) -> bool {
+    strip_base(&self.api, url).is_some()
+  }
+
+  /// The path of a url on the website, relative to the website root
+  pub fn web_path<'a>(&self, url: &'a Url) -> Option<&'a str> {
+    strip_base(&self.web, url)
+  }
+
+  /// The `owner/repo/commitish/path` of a raw file url
+  pub fn raw_path<'a>(&self, url: &'a Url) -> Option<&'a str> {
+    // raw.github.com is the legacy name of raw.githubusercontent.com
+    if self.is_github_com() && url.host_str() == Some("raw.github.com") {
+      return url.path().strip_prefix('/');
+    }
+
+    strip_base(&self.raw, url)
+  }
+
+  /// Split a url pointing to a file inside of a repo into its
+  /// owner, repo, branch (or commit) & path
+  pub fn repo_file<'a>(&self, url: &'a Url) -> Option<(&'a str, &'a str, &'a str, &'a str)> {
+    // raw files are checked first, as some forges serve them from the website
+    let (path, re) = match self.forge {
+      Forge::Github => {
+        if let Some(path) = self.raw_path(url) {
+          (path, regex!("^([^/]+)/([^/]+)/([^/]+)/(.+)"))
+        } else {
+          (
+            self.web_path(url)?,
+            regex!("^([^/]+)/([^/]+)/[^/]+/([^/]+)/(.+)"),
+          )
+        }
+      }
+      Forge::Gitlab => (
+        self.web_path(url)?,
+        regex!("^(.+?)/([^/]+)/-/(?:raw|blob)/([^/]+)/(.+)"),
+      ),
+      Forge::Gitea => (
+        self.web_path(url)?,
+        regex!("^([^/]+)/([^/]+)/(?:raw|src|media)/(?:branch/|tag/|commit/)?([^/]+)/(.+)"),
+      ),
+      Forge::Bitbucket => (
+        self.web_path(url)?,
+        regex!("^([^/]+)/([^/]+)/(?:raw|src)/([^/]+)/(.+)"),
+      ),
+      Forge::HuggingFace => (
+        self.web_path(url)?,
+        regex!("^((?:spaces/|datasets/)?[^/]+)/([^/]+)/(?:resolve|raw|blob)/([^/]+)/(.+)"),
+      ),
+    };
+
+    let res = re.captures(path).unwrap()?;
+    let group = |i| res.get(i).unwrap().as_str();
+
+    Some((group(1), group(2), group(3), group(4)))
+  }
+}
+
+/// Percent encode a path for use as a single segment, such as a GitLab project id
+pub(crate) fn encode(path: &str) -> String {
+  form_urlencoded::byte_serialize(path.as_bytes())
+    .collect::<String>()
+    .replace('+', "%20")
+}
+
+/// Percent encode each segment of a path, keeping the slashes
+pub(crate) fn encode_path(path: &str) -> String {
+  path.split('/').map(encode).collect::<Vec<_>>().join("/")
+}
+
+fn with_trailing_slash(mut url: Url) -> Url {
+  if !url.path().ends_with('/') {
+    url.set_path(&format!("{}/", url.path()));
+  }
+
+  url
+}
+
+fn strip_base<'a>(base: &Url, url: &'a Url) -> Option<&'a str> {
+  if url.host_str() != base.host_str() || url.port() != base.port() {
+    return None;
+  }
+
+  url.path().strip_prefix(base.path())
+}
@@ -0,0 +1,67 @@
+use crate::{
+  github_api::{get_repo_files, send, FileType},
+  RepoIconsClient, RepoIconsError,
+};
+use instant::Instant;
+use pulldown_cmark::{html, Options, Parser};
+
+/// Render the readme in a directory of the repo (`""` for the root) to HTML, for
+/// forges that don't have an API to do it for us. Along with the commit it was read at
+/// & the path of the readme
+pub async fn get_readme_html(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+  dir: &str,
+) -> Result<(String, String, String), RepoIconsError> {
+  let (commit_sha, files) = get_repo_files(client, owner, repo, git_ref).await?;
+  let dir = dir.to_lowercase();
+
+  let readme = files
+    .iter()
+    .filter(|file| {
+      let path = file.path.to_lowercase();
+      let (file_dir, filename) = path.rsplit_once('/').unwrap_or(("", &path));
+
+      matches!(file.r#type, FileType::Blob) && file_dir == dir && filename.starts_with("readme")
+    })
+    // prefer markdown over the other formats
+    .min_by_key(|file| !file.path.to_lowercase().ends_with(".md"))
+    .ok_or(RepoIconsError::NotFound { url: None })?;
+
+  let url = client
+    .host()
+    .file_url(&format!("{}/{}", owner, repo), &commit_sha, &readme.path);
+  let start = Instant::now();
+
+  let body = async {
+    send(client, &url, || client.get(url.clone()))
+      .await?
+      .text()
+      .await
+      .map_err(RepoIconsError::from)
+  }
+  .await;
+
+  info!("{}: {:?}", url, start.elapsed());
+
+  let body = body?;
+  if readme.path.to_lowercase().ends_with(".html") {
+    return Ok((body, commit_sha, readme.path.clone()));
+  }
+
+  // skip the front matter, which Hugging Face model cards use for their metadata
+  let markdown = match regex!(r"\A---\r?\n(?s:.*?)\r?\n---\r?\n")
+    .find(&body)
+    .unwrap()
+  {
+    Some(front_matter) => &body[front_matter.end()..],
+    None => &body[..],
+  };
+
+  let mut readme_html = String::new();
+  html::push_html(&mut readme_html, Parser::new_ext(markdown, Options::all()));
+
+  Ok((readme_html, commit_sha, readme.path.clone()))
+}
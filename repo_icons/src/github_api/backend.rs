@@ -0,0 +1,396 @@
+use crate::{
+  blacklist::{is_badge_url, is_blacklisted_homepage},
+  BoxFuture, ForgeHost, MaybeSend, MaybeSendFutureExt, MaybeSync, RepoIcon, RepoIconsError,
+};
+use reqwest::{header::HeaderMap, Client, Request, Response};
+use site_icons::{IconInfo, SiteIcons};
+use url::Url;
+
+/// Sends the requests made by a [`RepoIconsClient`](crate::RepoIconsClient), so that
+/// they can be served from somewhere other than the network, such as [`FixtureBackend`]
+pub trait GithubBackend: MaybeSend + MaybeSync {
+  fn execute<'a>(
+    &'a self,
+    http: &'a Client,
+    request: Request,
+  ) -> BoxFuture<'a, Result<Response, RepoIconsError>>;
+
+  /// The format & size of an icon, found by downloading the start of it
+  fn icon_info<'a>(
+    &'a self,
+    http: &'a Client,
+    url: &'a Url,
+    headers: HeaderMap,
+  ) -> BoxFuture<'a, Result<IconInfo, RepoIconsError>> {
+    load_icon_info(http, url, headers).boxed_maybe_send()
+  }
+
+  /// The icons on a homepage. `site_icons` fetches the pages with the client's
+  /// `http`, but they don't go through [`execute`](Self::execute)
+  fn homepage_icons<'a>(
+    &'a self,
+    http: &'a Client,
+    host: &'a ForgeHost,
+    homepage: &'a Url,
+    best_matches_only: bool,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+    load_homepage_icons(http, host, homepage, best_matches_only).boxed_maybe_send()
+  }
+}
+
+/// Sends requests over the network. This is the default
+pub struct HttpBackend;
+
+impl GithubBackend for HttpBackend {
+  fn execute<'a>(
+    &'a self,
+    http: &'a Client,
+    request: Request,
+  ) -> BoxFuture<'a, Result<Response, RepoIconsError>> {
+    async move { Ok(http.execute(request).await?) }.boxed_maybe_send()
+  }
+}
+
+async fn load_icon_info(
+  http: &Client,
+  url: &Url,
+  headers: HeaderMap,
+) -> Result<IconInfo, RepoIconsError> {
+  // the client's own proxy & user agent are used, rather than site_icons' default client
+  IconInfo::load_with_client(http.clone(), url.clone(), headers, None)
+    .await
+    .map_err(|err| RepoIconsError::InvalidImage {
+      url: Some(url.clone()),
+      message: err.to_string(),
+    })
+}
+
+async fn load_homepage_icons(
+  http: &Client,
+  host: &ForgeHost,
+  homepage: &Url,
+  best_matches_only: bool,
+) -> Result<Vec<RepoIcon>, RepoIconsError> {
+  let host = host.clone();
+  let mut icons = SiteIcons::new_with_blacklist(move |url| {
+    is_blacklisted_homepage(&host, url) || is_badge_url(&host, url)
+  })
+  .with_client(http.clone());
+
+  let entries = icons
+    .load_website(homepage.clone(), best_matches_only)
+    .await
+    .map_err(|err| RepoIconsError::Network {
+      url: Some(homepage.clone()),
+      message: err.to_string(),
+    })?;
+
+  Ok(
+    entries
+      .into_iter()
+      .map(|icon| RepoIcon::new(icon.url, (homepage.clone(), icon.kind).into(), icon.info))
+      .collect(),
+  )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use fixtures::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod fixtures {
+  use super::{load_homepage_icons, load_icon_info, GithubBackend};
+  use crate::{forges::encode, BoxFuture, ForgeHost, MaybeSendFutureExt, RepoIcon, RepoIconsError};
+  use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE, ETAG},
+    Client, Request, Response,
+  };
+  use serde::{de::DeserializeOwned, Deserialize, Serialize};
+  use site_icons::IconInfo;
+  use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+  };
+  use url::Url;
+
+  /// Only these response headers are recorded, so that fixtures stay deterministic
+  const RECORDED_HEADERS: [reqwest::header::HeaderName; 2] = [CONTENT_TYPE, ETAG];
+
+  /// Serves the responses that a [`RecordBackend`] saved to a directory, without
+  /// touching the network. Requests that weren't recorded fail
+  ///
+  /// ```
+  /// let client = RepoIconsClient::builder()
+  ///   .token(None::<String>)
+  ///   .backend(FixtureBackend::new("tests/fixtures/facebook-react"))
+  ///   .build()?;
+  /// ```
+  pub struct FixtureBackend {
+    dir: PathBuf,
+  }
+
+  impl FixtureBackend {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+      Self { dir: dir.into() }
+    }
+  }
+
+  impl GithubBackend for FixtureBackend {
+    fn execute<'a>(
+      &'a self,
+      _http: &'a Client,
+      request: Request,
+    ) -> BoxFuture<'a, Result<Response, RepoIconsError>> {
+      async move {
+        let path = response_path(&self.dir, &request);
+        let fixture = read::<ResponseFixture>(&path, request.url())?;
+
+        Ok(fixture.into_response())
+      }
+      .boxed_maybe_send()
+    }
+
+    fn icon_info<'a>(
+      &'a self,
+      _http: &'a Client,
+      url: &'a Url,
+      _headers: HeaderMap,
+    ) -> BoxFuture<'a, Result<IconInfo, RepoIconsError>> {
+      async move {
+        let path = icon_info_path(&self.dir, url);
+        Ok(read::<IconInfoFixture>(&path, url)?.info)
+      }
+      .boxed_maybe_send()
+    }
+
+    fn homepage_icons<'a>(
+      &'a self,
+      _http: &'a Client,
+      _host: &'a ForgeHost,
+      homepage: &'a Url,
+      best_matches_only: bool,
+    ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+      async move {
+        let path = homepage_path(&self.dir, homepage, best_matches_only);
+        Ok(read::<HomepageFixture>(&path, homepage)?.icons)
+      }
+      .boxed_maybe_send()
+    }
+  }
+
+  /// Sends requests over the network, saving every response to a
+  /// directory so that a [`FixtureBackend`] can serve them later
+  pub struct RecordBackend {
+    dir: PathBuf,
+  }
+
+  impl RecordBackend {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+      Self { dir: dir.into() }
+    }
+  }
+
+  impl GithubBackend for RecordBackend {
+    fn execute<'a>(
+      &'a self,
+      http: &'a Client,
+      request: Request,
+    ) -> BoxFuture<'a, Result<Response, RepoIconsError>> {
+      async move {
+        let path = response_path(&self.dir, &request);
+        let method = request.method().to_string();
+        let url = request.url().clone();
+
+        let res = http.execute(request).await?;
+        let status = res.status().as_u16();
+        let headers = RECORDED_HEADERS
+          .iter()
+          .filter_map(|name| {
+            let value = res.headers().get(name)?.to_str().ok()?;
+            Some((name.to_string(), value.to_string()))
+          })
+          .collect();
+        let body = match String::from_utf8(res.bytes().await?.to_vec()) {
+          Ok(text) => FixtureBody::Text(text),
+          Err(err) => FixtureBody::Bytes(err.into_bytes()),
+        };
+
+        let fixture = ResponseFixture {
+          method,
+          url,
+          status,
+          headers,
+          body,
+        };
+        write(&path, &fixture)?;
+
+        Ok(fixture.into_response())
+      }
+      .boxed_maybe_send()
+    }
+
+    fn icon_info<'a>(
+      &'a self,
+      http: &'a Client,
+      url: &'a Url,
+      headers: HeaderMap,
+    ) -> BoxFuture<'a, Result<IconInfo, RepoIconsError>> {
+      async move {
+        let info = load_icon_info(http, url, headers).await?;
+
+        let fixture = IconInfoFixture {
+          url: url.clone(),
+          info,
+        };
+        write(&icon_info_path(&self.dir, url), &fixture)?;
+
+        Ok(fixture.info)
+      }
+      .boxed_maybe_send()
+    }
+
+    fn homepage_icons<'a>(
+      &'a self,
+      http: &'a Client,
+      host: &'a ForgeHost,
+      homepage: &'a Url,
+      best_matches_only: bool,
+    ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+      async move {
+        let icons = load_homepage_icons(http, host, homepage, best_matches_only).await?;
+
+        let fixture = HomepageFixture {
+          homepage: homepage.clone(),
+          best_matches_only,
+          icons,
+        };
+        write(
+          &homepage_path(&self.dir, homepage, best_matches_only),
+          &fixture,
+        )?;
+
+        Ok(fixture.icons)
+      }
+      .boxed_maybe_send()
+    }
+  }
+
+  #[derive(Serialize, Deserialize)]
+  struct ResponseFixture {
+    method: String,
+    url: Url,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: FixtureBody,
+  }
+
+  /// JSON & HTML are kept readable, anything else is stored as bytes
+  #[derive(Serialize, Deserialize)]
+  #[serde(untagged)]
+  enum FixtureBody {
+    Text(String),
+    Bytes(Vec<u8>),
+  }
+
+  #[derive(Serialize, Deserialize)]
+  struct IconInfoFixture {
+    url: Url,
+    info: IconInfo,
+  }
+
+  #[derive(Serialize, Deserialize)]
+  struct HomepageFixture {
+    homepage: Url,
+    best_matches_only: bool,
+    icons: Vec<RepoIcon>,
+  }
+
+  impl ResponseFixture {
+    fn into_response(self) -> Response {
+      let mut res = http::Response::builder().status(self.status);
+      for (name, value) in &self.headers {
+        res = res.header(name, value);
+      }
+
+      let body = match self.body {
+        FixtureBody::Text(text) => text.into_bytes(),
+        FixtureBody::Bytes(bytes) => bytes,
+      };
+
+      res.body(body).unwrap().into()
+    }
+  }
+
+  fn response_path(dir: &Path, request: &Request) -> PathBuf {
+    let body = request
+      .body()
+      .and_then(|body| body.as_bytes())
+      .unwrap_or_default();
+
+    fixture_path(dir, request.method().as_str(), request.url(), body)
+  }
+
+  fn icon_info_path(dir: &Path, url: &Url) -> PathBuf {
+    fixture_path(dir, "INFO", url, &[])
+  }
+
+  fn homepage_path(dir: &Path, homepage: &Url, best_matches_only: bool) -> PathBuf {
+    fixture_path(dir, "HOMEPAGE", homepage, &[best_matches_only as u8])
+  }
+
+  /// A readable name for the request, along with a hash of all of it,
+  /// as URLs can be too long for a filename & POSTs share the same URL
+  fn fixture_path(dir: &Path, method: &str, url: &Url, body: &[u8]) -> PathBuf {
+    let mut name = encode(&url[url::Position::BeforeHost..url::Position::AfterPath]);
+    name.truncate(80);
+
+    let mut hash = Fnv::default();
+    hash.write(method.as_bytes());
+    hash.write(url.as_str().as_bytes());
+    hash.write(body);
+
+    dir.join(format!("{}-{}-{:016x}.json", method, name, hash.0))
+  }
+
+  fn read<T: DeserializeOwned>(path: &Path, url: &Url) -> Result<T, RepoIconsError> {
+    let fixture = fs::read_to_string(path).map_err(|_| {
+      RepoIconsError::other(format!(
+        "no fixture for {} at {}, record it with a RecordBackend",
+        url,
+        path.display()
+      ))
+    })?;
+
+    serde_json::from_str(&fixture).map_err(RepoIconsError::other)
+  }
+
+  fn write<T: Serialize>(path: &Path, fixture: &T) -> Result<(), RepoIconsError> {
+    let json = serde_json::to_string_pretty(fixture).map_err(RepoIconsError::other)?;
+
+    path
+      .parent()
+      .map(fs::create_dir_all)
+      .transpose()
+      .and_then(|_| fs::write(path, json))
+      .map_err(RepoIconsError::other)
+  }
+
+  /// FNV-1a, which unlike the std hashers is stable between
+  /// versions, so recorded fixtures keep their names
+  struct Fnv(u64);
+
+  impl Default for Fnv {
+    fn default() -> Self {
+      Self(0xcbf29ce484222325)
+    }
+  }
+
+  impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+      for byte in bytes {
+        self.0 ^= *byte as u64;
+        self.0 = self.0.wrapping_mul(0x100000001b3);
+      }
+    }
+  }
+}
@@ -0,0 +1,282 @@
+use super::{now, File, Repo, Revalidated};
+use crate::{Cache, CacheKind, ForgeHost, RepoIconsError};
+use cached::{Cached, SizedCache};
+use futures::channel::oneshot;
+use instant::Duration;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+  collections::HashMap,
+  future::Future,
+  sync::{Arc, Mutex},
+};
+
+/// Results by key, along with the loads that are in flight. Concurrent
+/// misses on a key wait for the in flight load, instead of making their own request.
+///
+/// Recent results are kept in memory, in front of the [`Cache`] store of the client
+pub(crate) struct CacheSlot<T> {
+  kind: CacheKind,
+  ttl: Duration,
+  /// prepended to keys in the store, so that hosts don't share entries
+  prefix: String,
+  store: Arc<dyn Cache>,
+  results: Mutex<SizedCache<String, Entry<T>>>,
+  in_flight: Mutex<HashMap<String, Vec<oneshot::Sender<Result<T, RepoIconsError>>>>>,
+}
+
+/// A cached result, which is also the JSON written to the store
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry<T> {
+  /// unix timestamp (in seconds) of when the entry expires. Expired
+  /// entries are kept around, so that they can be revalidated
+  expires: u64,
+  /// of the response the result was loaded from
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  etag: Option<String>,
+  value: Result<T, RepoIconsError>,
+}
+
+impl<T> Entry<T> {
+  fn is_fresh(&self) -> bool {
+    self.expires > now()
+  }
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> CacheSlot<T> {
+  fn new(
+    kind: CacheKind,
+    host: &ForgeHost,
+    store: Arc<dyn Cache>,
+    ttls: &HashMap<CacheKind, Duration>,
+  ) -> Self {
+    let web = host.web();
+
+    Self {
+      kind,
+      ttl: ttls
+        .get(&kind)
+        .copied()
+        .unwrap_or_else(|| kind.default_ttl()),
+      prefix: web[url::Position::BeforeHost..url::Position::AfterPath].to_string(),
+      store,
+      results: Mutex::new(SizedCache::with_size(100)),
+      in_flight: Mutex::new(HashMap::new()),
+    }
+  }
+
+  fn store_key(&self, key: &str) -> String {
+    format!("{}{}/{}", self.prefix, self.kind.name(), key)
+  }
+
+  fn get_fresh(&self, key: &str) -> Option<Result<T, RepoIconsError>> {
+    self
+      .results
+      .lock()
+      .unwrap()
+      .cache_get(key)
+      .filter(|entry| entry.is_fresh())
+      .map(|entry| entry.value.clone())
+  }
+
+  /// The entry in memory or in the store, even if it has expired
+  fn get(&self, key: &str) -> Option<Entry<T>> {
+    let entry = self.results.lock().unwrap().cache_get(key).cloned();
+
+    match entry {
+      Some(entry) if entry.is_fresh() => Some(entry),
+      // another client sharing the store may have refreshed it
+      entry => self.get_stored(key).or(entry),
+    }
+  }
+
+  fn get_stored(&self, key: &str) -> Option<Entry<T>> {
+    let entry = self.store.get(&self.store_key(key))?;
+    let entry = match serde_json::from_str::<Entry<T>>(&entry) {
+      Ok(entry) => entry,
+      Err(err) => {
+        warn!("ignoring cached {}: {}", self.store_key(key), err);
+        return None;
+      }
+    };
+
+    self
+      .results
+      .lock()
+      .unwrap()
+      .cache_set(key.to_string(), entry.clone());
+
+    Some(entry)
+  }
+
+  fn set(&self, key: &str, value: Result<T, RepoIconsError>, etag: Option<String>) {
+    let entry = Entry {
+      expires: now() + self.ttl.as_secs(),
+      etag,
+      value,
+    };
+
+    match serde_json::to_string(&entry) {
+      Ok(json) => self.store.set(&self.store_key(key), json, self.ttl),
+      Err(err) => warn!("failed to cache {}: {}", self.store_key(key), err),
+    }
+
+    self
+      .results
+      .lock()
+      .unwrap()
+      .cache_set(key.to_string(), entry);
+  }
+}
+
+/// The API responses cached by a [`RepoIconsClient`](crate::RepoIconsClient)
+pub(crate) struct GithubCaches {
+  pub(crate) repos: CacheSlot<Repo>,
+  pub(crate) repo_files: CacheSlot<(String, Vec<File>)>,
+  pub(crate) redirected_users: CacheSlot<(String, bool)>,
+  /// the rendered root readme of GitHub repos, at the default branch
+  pub(crate) readmes: CacheSlot<String>,
+}
+
+impl GithubCaches {
+  pub(crate) fn new(
+    host: &ForgeHost,
+    store: Arc<dyn Cache>,
+    ttls: &HashMap<CacheKind, Duration>,
+  ) -> Self {
+    Self {
+      repos: CacheSlot::new(CacheKind::Repo, host, store.clone(), ttls),
+      repo_files: CacheSlot::new(CacheKind::RepoFiles, host, store.clone(), ttls),
+      redirected_users: CacheSlot::new(CacheKind::RedirectedUser, host, store.clone(), ttls),
+      readmes: CacheSlot::new(CacheKind::Readme, host, store, ttls),
+    }
+  }
+}
+
+pub(crate) fn cache_key(owner: &str, repo: &str) -> String {
+  format!("{}/{}", owner.to_lowercase(), repo.to_lowercase())
+}
+
+/// Return the cached result for the key, or load & cache it.
+/// Only successes & not founds are cached, transient errors get retried.
+///
+/// While a key is loading, other callers wait for that load & share its result
+pub(crate) async fn get_or_load<T, F>(
+  cache: &CacheSlot<T>,
+  key: String,
+  load: F,
+) -> Result<T, RepoIconsError>
+where
+  T: Clone + Serialize + DeserializeOwned,
+  F: Future<Output = Result<T, RepoIconsError>>,
+{
+  get_or_revalidate(cache, key, |_| async {
+    load.await.map(|value| Revalidated::Modified(value, None))
+  })
+  .await
+}
+
+/// Like [`get_or_load`], but expired results are loaded with the ETag they were
+/// cached with, and kept for another TTL if they weren't modified
+pub(crate) async fn get_or_revalidate<T, L, F>(
+  cache: &CacheSlot<T>,
+  key: String,
+  load: L,
+) -> Result<T, RepoIconsError>
+where
+  T: Clone + Serialize + DeserializeOwned,
+  L: FnOnce(Option<String>) -> F,
+  F: Future<Output = Result<Revalidated<T>, RepoIconsError>>,
+{
+  loop {
+    let waiting = {
+      // held while checking the results, so a load can't finish in between
+      let mut in_flight = cache.in_flight.lock().unwrap();
+
+      if let Some(res) = cache.get_fresh(&key) {
+        return res;
+      }
+
+      match in_flight.get_mut(&key) {
+        Some(waiters) => {
+          let (sender, receiver) = oneshot::channel();
+          waiters.push(sender);
+          receiver
+        }
+        None => {
+          in_flight.insert(key.clone(), Vec::new());
+          break;
+        }
+      }
+    };
+
+    // the load was dropped before it finished, so try again
+    if let Ok(res) = waiting.await {
+      return res;
+    }
+  }
+
+  let mut in_flight = InFlight {
+    cache,
+    key: &key,
+    finished: false,
+  };
+
+  let res = match cache.get(&key) {
+    Some(entry) if entry.is_fresh() => entry.value,
+    stale => {
+      let etag = stale.as_ref().and_then(|entry| entry.etag.clone());
+
+      match (load(etag).await, stale) {
+        (Ok(Revalidated::Modified(value, etag)), _) => {
+          cache.set(&key, Ok(value.clone()), etag);
+          Ok(value)
+        }
+        (Ok(Revalidated::NotModified), Some(entry)) => {
+          cache.set(&key, entry.value.clone(), entry.etag);
+          entry.value
+        }
+        (Ok(not_modified), None) => not_modified.modified(),
+        (Err(err), _) => {
+          if matches!(err, RepoIconsError::NotFound { .. }) {
+            cache.set(&key, Err(err.clone()), None);
+          }
+          Err(err)
+        }
+      }
+    }
+  };
+
+  let waiters = cache.in_flight.lock().unwrap().remove(&key);
+  in_flight.finished = true;
+
+  for waiter in waiters.into_iter().flatten() {
+    let _ = waiter.send(res.clone());
+  }
+
+  res
+}
+
+/// Cache a result that was loaded some other way, such as in a batch
+pub(crate) fn set_cached<T: Clone + Serialize + DeserializeOwned>(
+  cache: &CacheSlot<T>,
+  key: String,
+  res: Result<T, RepoIconsError>,
+) {
+  cache.set(&key, res, None);
+}
+
+/// Clears the in flight load of a key if it's dropped before finishing,
+/// which wakes up the waiters so that one of them can take over
+struct InFlight<'a, T> {
+  cache: &'a CacheSlot<T>,
+  key: &'a str,
+  finished: bool,
+}
+
+impl<T> Drop for InFlight<'_, T> {
+  fn drop(&mut self) {
+    if !self.finished {
+      self.cache.in_flight.lock().unwrap().remove(self.key);
+    }
+  }
+}
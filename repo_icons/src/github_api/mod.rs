@@ -1,7 +1,13 @@
+mod backend;
+mod caches;
 pub mod readme;
 mod repo;
 mod repo_icon_files;
+mod request;
 
+pub use backend::*;
+pub(crate) use caches::*;
 pub use readme::*;
 pub use repo::*;
 pub use repo_icon_files::*;
+pub use request::*;
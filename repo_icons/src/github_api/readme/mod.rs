@@ -3,6 +3,11 @@ pub mod readme_image;
 
 pub use readme_image::*;
 
+use crate::{
+  forges::{encode, get_readme_html, Forge},
+  github_api::{cache_key, get_or_revalidate, send_if_modified, Revalidated},
+  RepoIconsClient, RepoIconsError,
+};
 use instant::Instant;
 use primary_heading::PrimaryHeading;
 use scraper::Html;
@@ -16,32 +21,99 @@ use vec1::Vec1;
 pub struct Readme(Vec1<ReadmeImage>);
 
 impl Readme {
-  pub async fn load(owner: &str, repo: &str) -> Option<Readme> {
-    let url = format!("repos/{}/{}/readme", owner, repo);
-    let start = Instant::now();
+  /// Load the images in the readme, or `None` if it has no images
+  pub async fn load(owner: &str, repo: &str) -> Result<Option<Readme>, RepoIconsError> {
+    Self::load_with_client(RepoIconsClient::global(), owner, repo).await
+  }
 
-    let body = async {
-      gh_api_get!("{}", url)
-        .header("Accept", "application/vnd.github.html")
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await
+  pub async fn load_with_client(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+  ) -> Result<Option<Readme>, RepoIconsError> {
+    Self::load_with_subpath(client, owner, repo, None).await
+  }
+
+  /// Load the images in the readme of a directory inside the repo,
+  /// falling back to the root readme when the directory doesn't have one
+  pub async fn load_with_subpath(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+    subpath: Option<&str>,
+  ) -> Result<Option<Readme>, RepoIconsError> {
+    Self::load_with_ref(client, owner, repo, None, subpath).await
+  }
+
+  /// Load the images in the readme as of a branch, tag or commit,
+  /// see [`load_with_subpath`](Self::load_with_subpath)
+  pub async fn load_with_ref(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+    git_ref: Option<&str>,
+    subpath: Option<&str>,
+  ) -> Result<Option<Readme>, RepoIconsError> {
+    if let Some(subpath) = subpath.map(|subpath| subpath.trim_matches('/')) {
+      if !subpath.is_empty() {
+        match Self::load_in_dir(client, owner, repo, git_ref, subpath).await {
+          Err(RepoIconsError::NotFound { .. }) => {}
+          res => return res,
+        }
+      }
     }
-    .await;
 
-    info!("{}: {:?}", url, start.elapsed());
+    Self::load_in_dir(client, owner, repo, git_ref, "").await
+  }
 
-    let document = Html::parse_document(&body.ok()?);
+  async fn load_in_dir(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+    git_ref: Option<&str>,
+    dir: &str,
+  ) -> Result<Option<Readme>, RepoIconsError> {
+    let slug = format!("{}/{}", owner, repo);
+    let (body, base) = match client.host().forge() {
+      Forge::Github => {
+        let body = get_github_readme_html(client, owner, repo, git_ref, dir).await?;
+        let mut base = client
+          .host()
+          .readme_base_url(&slug, git_ref.unwrap_or("HEAD"));
+        if !dir.is_empty() {
+          base = base.join(&format!("{}/", dir))?;
+        }
+
+        (body, base)
+      }
+      _ => {
+        let (body, commit_sha, path) = get_readme_html(client, owner, repo, git_ref, dir).await?;
+        // relative links are resolved against the readme itself
+        let base = client
+          .host()
+          .readme_base_url(&slug, &commit_sha)
+          .join(&path)?;
+
+        (body, base)
+      }
+    };
 
-    let primary_heading = &mut PrimaryHeading::new(&document);
+    // the document isn't Send, so everything is read from it before any awaits
+    let elements = {
+      let document = Html::parse_document(&body);
+      let primary_heading = &mut PrimaryHeading::new(&document);
+
+      document
+        .select(selector!("img[src]"))
+        .filter_map(|element_ref| {
+          ImageElement::new(client.host(), &base, &element_ref, primary_heading)
+        })
+        .collect::<Vec<_>>()
+    };
 
     let mut images = Vec::new();
-    for element_ref in document.select(selector!("img[src]")) {
-      if let Some(image) = ReadmeImage::get(owner, repo, &element_ref, primary_heading).await {
-        images.push(image);
-      }
+    for element in elements {
+      images.push(ReadmeImage::get(client, owner, repo, element).await);
     }
 
     let mut iter = images.iter_mut().enumerate().peekable();
@@ -59,10 +131,73 @@ impl Readme {
 
     images.sort();
 
-    images.try_into().ok().map(Readme)
+    Ok(images.try_into().ok().map(Readme))
   }
 }
 
+async fn get_github_readme_html(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+  dir: &str,
+) -> Result<String, RepoIconsError> {
+  // only the root readme is cached, which is what batches preload
+  if dir.is_empty() && git_ref.is_none() {
+    return get_or_revalidate(
+      &client.caches().readmes,
+      cache_key(owner, repo),
+      |etag| async move {
+        load_github_readme_html(client, owner, repo, None, dir, etag.as_deref()).await
+      },
+    )
+    .await;
+  }
+
+  load_github_readme_html(client, owner, repo, git_ref, dir, None)
+    .await?
+    .modified()
+}
+
+async fn load_github_readme_html(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+  dir: &str,
+  etag: Option<&str>,
+) -> Result<Revalidated<String>, RepoIconsError> {
+  let mut path = if dir.is_empty() {
+    format!("repos/{}/{}/readme", owner, repo)
+  } else {
+    format!("repos/{}/{}/readme/{}", owner, repo, dir)
+  };
+  if let Some(git_ref) = git_ref {
+    path = format!("{}?ref={}", path, encode(git_ref));
+  }
+  let url = client.host().api_url(&path);
+  let start = Instant::now();
+
+  let body = async {
+    let res = send_if_modified(client, &url, etag, || {
+      client
+        .api_get(&path)
+        .header("Accept", "application/vnd.github.html")
+    })
+    .await?;
+
+    match res {
+      Revalidated::Modified(res, etag) => Ok(Revalidated::Modified(res.text().await?, etag)),
+      Revalidated::NotModified => Ok(Revalidated::NotModified),
+    }
+  }
+  .await;
+
+  info!("{}: {:?}", url, start.elapsed());
+
+  body
+}
+
 impl IntoIterator for Readme {
   type Item = ReadmeImage;
   type IntoIter = std::vec::IntoIter<Self::Item>;
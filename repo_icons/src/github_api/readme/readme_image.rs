@@ -5,8 +5,8 @@ use crate::{
     get_branch_and_path, is_same_repo,
     repo::{qualify_repo_raw_url, Repo},
   },
+  ForgeHost, RepoIconsClient, Score,
 };
-use gh_api::get_token;
 use scraper::ElementRef;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -49,40 +49,49 @@ pub struct ReadmeImage {
   pub has_size_attrs: bool,
 }
 
-impl ReadmeImage {
-  pub async fn get(
-    owner: &str,
-    repo: &str,
+/// The attributes of an `<img>` in the readme, & of the elements around it
+pub struct ImageElement {
+  src: Url,
+  cdn_src: Option<Url>,
+  alt: String,
+  /// the links the image is inside of, innermost first
+  links: Vec<Option<Url>>,
+  is_align_center: bool,
+  has_size_attrs: bool,
+  in_primary_heading: bool,
+}
+
+impl ImageElement {
+  /// `None` for badges
+  pub(crate) fn new(
+    host: &ForgeHost,
+    base: &Url,
     elem_ref: &ElementRef<'_>,
     primary_heading: &mut PrimaryHeading<'_>,
   ) -> Option<Self> {
-    let owner = owner.to_lowercase();
-    let repo = repo.to_lowercase();
-
     let elem = elem_ref.value();
 
     let src = elem
       .attr("data-canonical-src")
       .or(elem.attr("src"))
-      .and_then(|src| qualify_repo_raw_url(&owner, &repo, src).ok())
-      .unwrap();
+      .and_then(|src| qualify_repo_raw_url(base, src).ok())?;
 
     let alt = elem
       .attr("alt")
       .map(|alt| alt.to_lowercase())
       .unwrap_or(String::new());
 
-    if is_badge_url(&src) || is_badge_text(&alt) {
+    if is_badge_url(host, &src) || is_badge_text(&alt) {
       return None;
     }
 
     let cdn_src = elem
       .attr("data-canonical-src")
       .and(elem.attr("src"))
-      .and_then(|src| qualify_repo_raw_url(&owner, &repo, src).ok());
+      .and_then(|src| qualify_repo_raw_url(base, src).ok());
 
     let mut is_align_center = false;
-    let mut links_to = None;
+    let mut links = Vec::new();
     for elem_ref in elem_ref.ancestors().map(ElementRef::wrap).flatten() {
       let element = elem_ref.value();
 
@@ -90,28 +99,63 @@ impl ReadmeImage {
         is_align_center = true;
       }
 
-      if element.name() == "a" && links_to.is_none() {
-        links_to = match element
-          .attr("href")
-          .and_then(|href| qualify_repo_raw_url(&owner, &repo, href).ok())
-        {
-          Some(href) => {
-            // if the img points to the same url as the link
-            // then its a default url generated by github
-            let mut img_blob_url = src.clone();
-            img_blob_url.set_path(&src.path().replacen("/raw/", "/blob/", 1));
-            if href != img_blob_url {
-              is_link_to_project(&owner, &repo, &href).await
-            } else {
-              None
-            }
-          }
-          None => None,
-        };
+      if element.name() == "a" {
+        links.push(
+          element
+            .attr("href")
+            .and_then(|href| qualify_repo_raw_url(base, href).ok()),
+        );
+      }
+    }
+
+    Some(ImageElement {
+      src,
+      cdn_src,
+      alt,
+      links,
+      is_align_center,
+      has_size_attrs: elem.attr("width").or(elem.attr("height")).is_some(),
+      in_primary_heading: primary_heading.contains(elem_ref),
+    })
+  }
+}
+
+impl ReadmeImage {
+  pub async fn get(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+    element: ImageElement,
+  ) -> Self {
+    let owner = owner.to_lowercase();
+    let repo = repo.to_lowercase();
+
+    let ImageElement {
+      src,
+      cdn_src,
+      alt,
+      links,
+      is_align_center,
+      has_size_attrs,
+      in_primary_heading,
+    } = element;
+
+    let mut links_to = None;
+    for href in links.into_iter().flatten() {
+      // if the img points to the same url as the link
+      // then its a default url generated by github
+      let mut img_blob_url = src.clone();
+      img_blob_url.set_path(&src.path().replacen("/raw/", "/blob/", 1));
+      if href != img_blob_url {
+        links_to = is_link_to_project(client, &owner, &repo, &href).await;
+      }
+
+      if links_to.is_some() {
+        break;
       }
     }
 
-    let branch_and_path = get_branch_and_path(&owner, &repo, &src).await;
+    let branch_and_path = get_branch_and_path(client, &owner, &repo, &src).await;
     let keyword_mentions = {
       let mut mentions = HashSet::new();
 
@@ -138,82 +182,90 @@ impl ReadmeImage {
 
     let src = cdn_src.unwrap_or({
       if let Some((branch, path)) = &branch_and_path {
-        if let Ok(Repo { private: true, .. }) = Repo::load(&owner, &repo).await {
-          headers.insert(
-            "Authorization".to_string(),
-            format!("Bearer {}", get_token().unwrap()).to_string(),
-          );
-        }
+        let slug = format!("{}/{}", owner, repo);
+
+        if let Ok(Repo { private: true, .. }) = Repo::load_with_client(client, &owner, &repo).await
+        {
+          if let Some(token) = client.token() {
+            headers.insert(
+              "Authorization".to_string(),
+              client.host().auth_header(&token),
+            );
+          }
 
-        Url::parse(&format!(
-          "https://raw.githubusercontent.com/{}/{}/{}/{}",
-          owner, repo, branch, path
-        ))
-        .unwrap()
+          client.host().file_url(&slug, branch, path)
+        } else {
+          client.host().raw_url(&slug, branch, path)
+        }
       } else {
         src
       }
     });
 
-    Some(ReadmeImage {
+    ReadmeImage {
       src,
       headers,
-      in_primary_heading: primary_heading.contains(elem_ref),
+      in_primary_heading,
       edge_of_primary_heading: false,
       keyword_mentions,
       sourced_from_repo: branch_and_path.is_some(),
       links_to,
       is_align_center,
-      has_size_attrs: elem.attr("width").or(elem.attr("height")).is_some(),
-    })
+      has_size_attrs,
+    }
   }
 
   pub fn weight(&self) -> u8 {
-    let mut weight = 0;
+    self.score().weight
+  }
+
+  /// The weight of the image, broken down by the rules that matched it
+  pub fn score(&self) -> Score {
+    let mut score = Score::default();
 
     if self.in_primary_heading {
-      weight += 2;
+      score.add("in primary heading", 2);
 
       if self.is_align_center {
-        weight += 2;
+        score.add("centered in primary heading", 2);
       }
 
       if self.has_size_attrs {
-        weight += 2;
+        score.add("sized in primary heading", 2);
       }
 
       if self.sourced_from_repo {
-        weight += 4;
+        score.add("sourced from repo in primary heading", 4);
       }
     };
 
     if self.edge_of_primary_heading {
-      weight += 4;
+      score.add("edge of primary heading", 4);
     }
 
     match self.links_to {
       Some(ProjectLink::Website) => {
-        weight += 8;
+        score.add("links to website", 8);
       }
       Some(ProjectLink::Repo) => {
-        weight += 4;
+        score.add("links to repo", 4);
       }
       None => {}
     }
 
     if self.keyword_mentions.contains(&KeywordMention::Logo) {
-      weight += 16
+      score.add("mentions logo", 16);
     }
 
     if self.keyword_mentions.contains(&KeywordMention::Banner) {
-      weight += 8
+      score.add("mentions banner", 8);
     }
 
     if self.keyword_mentions.contains(&KeywordMention::RepoName) {
-      weight += 4
+      score.add("mentions repo name", 4);
     }
 
-    weight
+    score
   }
 }
 
@@ -230,7 +282,12 @@ impl PartialOrd for ReadmeImage {
 }
 
 /// Check if a given url is a project link.
-async fn is_link_to_project(owner: &str, repo: &str, url: &Url) -> Option<ProjectLink> {
+async fn is_link_to_project(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  url: &Url,
+) -> Option<ProjectLink> {
   let domain = url.domain()?.to_lowercase();
 
   // check for github pages
@@ -248,7 +305,7 @@ async fn is_link_to_project(owner: &str, repo: &str, url: &Url) -> Option<Projec
     // USERNAME.github.io/REPO
     if let Some(res) = regex!("^/([^/]+)").captures(url.path()).unwrap() {
       let other_repo = &res[1];
-      if is_same_repo((owner, repo), (user, other_repo)).await {
+      if is_same_repo(client, (owner, repo), (user, other_repo)).await {
         return Some(ProjectLink::Website);
       }
     }
@@ -257,7 +314,7 @@ async fn is_link_to_project(owner: &str, repo: &str, url: &Url) -> Option<Projec
   if let Ok(Repo {
     homepage: Some(homepage),
     ..
-  }) = Repo::load(owner, repo).await
+  }) = Repo::load_with_client(client, owner, repo).await
   {
     if homepage
       .domain()
@@ -269,7 +326,10 @@ async fn is_link_to_project(owner: &str, repo: &str, url: &Url) -> Option<Projec
     }
   }
 
-  if get_branch_and_path(owner, repo, url).await.is_some() {
+  if get_branch_and_path(client, owner, repo, url)
+    .await
+    .is_some()
+  {
     return Some(ProjectLink::Repo);
   };
 
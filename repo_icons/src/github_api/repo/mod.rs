@@ -1,5 +1,7 @@
 mod repo;
+mod repo_batch;
 mod repo_redirect;
 
 pub use repo::*;
+pub(crate) use repo_batch::*;
 pub use repo_redirect::*;
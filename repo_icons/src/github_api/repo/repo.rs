@@ -1,18 +1,23 @@
 use super::is_same_repo;
-use crate::blacklist::is_blacklisted_homepage;
-use cached::proc_macro::cached;
-use cached::SizedCache;
+use crate::{
+  blacklist::is_blacklisted_homepage,
+  forges::{bitbucket, gitea, gitlab, hugging_face, Forge},
+  github_api::{cache_key, get_or_revalidate, send_if_modified, Revalidated},
+  RepoIconsClient, RepoIconsError,
+};
 use instant::Instant;
-use serde::{de, Deserialize};
+use serde::{de, Deserialize, Serialize};
 use url::Url;
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct User {
   pub login: String,
   pub r#type: String,
+  #[serde(default)]
+  pub avatar_url: Option<Url>,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Repo {
   pub owner: User,
   pub name: String,
@@ -20,52 +25,75 @@ pub struct Repo {
   pub private: bool,
   #[serde(deserialize_with = "deserialize_homepage")]
   pub homepage: Option<Url>,
-}
-
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum RepoResponse {
-  Repo(Repo),
-  Message { message: String },
+  /// The avatar of the repo itself, on forges that have them
+  #[serde(default)]
+  pub avatar: Option<Url>,
 }
 
 impl Repo {
-  pub async fn load(owner: &str, repo: &str) -> Result<Self, String> {
-    get_repo_cached(owner, repo).await
+  pub async fn load(owner: &str, repo: &str) -> Result<Self, RepoIconsError> {
+    Self::load_with_client(RepoIconsClient::global(), owner, repo).await
+  }
+
+  pub async fn load_with_client(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+  ) -> Result<Self, RepoIconsError> {
+    get_or_revalidate(
+      &client.caches().repos,
+      cache_key(owner, repo),
+      |etag| async move {
+        let (mut repo, etag) = match client.host().forge() {
+          Forge::Github => match get_repo(client, owner, repo, etag.as_deref()).await? {
+            Revalidated::Modified(repo, etag) => (repo, etag),
+            Revalidated::NotModified => return Ok(Revalidated::NotModified),
+          },
+          Forge::Gitlab => (gitlab::get_repo(client, owner, repo).await?, None),
+          Forge::Gitea => (gitea::get_repo(client, owner, repo).await?, None),
+          Forge::Bitbucket => (bitbucket::get_repo(client, owner, repo).await?, None),
+          Forge::HuggingFace => (hugging_face::get_repo(client, owner, repo).await?, None),
+        };
+
+        repo.homepage = repo
+          .homepage
+          .filter(|homepage| !is_blacklisted_homepage(client.host(), homepage));
+
+        Ok(Revalidated::Modified(repo, etag))
+      },
+    )
+    .await
   }
 }
 
-#[cached(
-  sync_writes = true,
-  type = "SizedCache<String, Result<Repo, String>>",
-  create = "{ SizedCache::with_size(100) }",
-  convert = r#"{ format!("{}/{}", owner.to_lowercase(), repo.to_lowercase()) }"#
-)]
-async fn get_repo_cached(owner: &str, repo: &str) -> Result<Repo, String> {
-  let url = format!("repos/{}/{}", owner, repo);
+async fn get_repo(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  etag: Option<&str>,
+) -> Result<Revalidated<Repo>, RepoIconsError> {
+  let path = format!("repos/{}/{}", owner, repo);
+  let url = client.host().api_url(&path);
   let start = Instant::now();
 
   let response = async {
-    gh_api_get!("{}", url)
-      .send()
-      .await?
-      .json::<RepoResponse>()
-      .await
+    match send_if_modified(client, &url, etag, || client.api_get(&path)).await? {
+      Revalidated::Modified(res, etag) => {
+        Ok(Revalidated::Modified(res.json::<Repo>().await?, etag))
+      }
+      Revalidated::NotModified => Ok(Revalidated::NotModified),
+    }
   }
-  .await
-  .map_err(|e| format!("{}: {:?}", url, e));
+  .await;
 
   info!("{}: {:?}", url, start.elapsed());
 
-  match response? {
-    RepoResponse::Repo(repo) => Ok(repo),
-    RepoResponse::Message { message } => Err(message),
-  }
+  response
 }
 
-pub fn qualify_repo_raw_url(owner: &str, repo: &str, path: &str) -> Result<Url, url::ParseError> {
-  let link_base = Url::parse(&format!("https://github.com/{}/{}/raw/HEAD/", owner, repo)).unwrap();
-
+/// Resolve a link in the readme, relative to the base from
+/// [`ForgeHost::readme_base_url`](crate::ForgeHost::readme_base_url)
+pub fn qualify_repo_raw_url(link_base: &Url, path: &str) -> Result<Url, url::ParseError> {
   let mut path = path.to_string();
   if path.starts_with("/") {
     path = format!(".{}", path);
@@ -75,56 +103,32 @@ pub fn qualify_repo_raw_url(owner: &str, repo: &str, path: &str) -> Result<Url,
 }
 
 /// Check if a given url points to a file located inside the repo.
-pub async fn get_branch_and_path(owner: &str, repo: &str, url: &Url) -> Option<(String, String)> {
-  let domain = if let Some(domain) = url.domain() {
-    domain.to_lowercase()
-  } else {
-    return None;
+pub async fn get_branch_and_path(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  url: &Url,
+) -> Option<(String, String)> {
+  let (other_owner, other_repo, branch, path) = client.host().repo_file(url)?;
+
+  if is_same_repo(client, (owner, repo), (other_owner, other_repo)).await {
+    return Some((branch.into(), path.into()));
   };
 
-  match &domain[..] {
-    "raw.githubusercontent.com" | "raw.github.com" | "github.com" => {
-      let re = if domain == "github.com" {
-        regex!("^/([^/]+)/([^/]+)/[^/]+/([^/]+)/(.+)")
-      } else {
-        regex!("^/([^/]+)/([^/]+)/([^/]+)/(.+)")
-      };
-
-      if let Some(res) = re.captures(url.path()).unwrap() {
-        let other_owner = &res[1];
-        let other_repo = &res[2];
-
-        if is_same_repo((owner, repo), (other_owner, other_repo)).await {
-          let branch = &res[3];
-          let path = &res[4];
-          return Some((branch.into(), path.into()));
-        };
-      }
-    }
-    _ => {}
+  None
+}
+
+/// Parse the homepage set on a repo, which often doesn't include the scheme
+pub(crate) fn parse_homepage(url: &str) -> Option<Url> {
+  if url.is_empty() {
+    return None;
   }
 
-  None
+  Url::parse(url)
+    .or_else(|_| Url::parse(&format!("http://{}", url)))
+    .ok()
 }
 
 fn deserialize_homepage<'de, D: de::Deserializer<'de>>(d: D) -> Result<Option<Url>, D::Error> {
-  Deserialize::deserialize(d).map(|url: Option<&str>| {
-    url.and_then(|url| {
-      if url.is_empty() {
-        return None;
-      }
-
-      if let Ok(homepage) =
-        Url::parse(url.as_ref()).or_else(|_| Url::parse(&format!("http://{}", url)))
-      {
-        if is_blacklisted_homepage(&homepage) {
-          None
-        } else {
-          Some(homepage)
-        }
-      } else {
-        None
-      }
-    })
-  })
+  Deserialize::deserialize(d).map(|url: Option<&str>| url.and_then(parse_homepage))
 }
@@ -0,0 +1,191 @@
+use super::{parse_homepage, Repo, User};
+use crate::{
+  blacklist::is_blacklisted_homepage,
+  github_api::{cache_key, send, set_cached},
+  RepoIconsClient, RepoIconsError,
+};
+use instant::Instant;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use url::Url;
+
+/// How many repos are looked up in a single GraphQL query
+pub(crate) const GRAPHQL_BATCH_SIZE: usize = 50;
+
+/// The fields of [`Repo`], along with the entries of the
+/// directories GitHub looks for the readme in
+const REPO_FIELDS: &str = r#"
+  name
+  owner { __typename login avatarUrl }
+  defaultBranchRef { name }
+  isPrivate
+  homepageUrl
+  root: object(expression: "HEAD:") { ...Entries }
+  dotGithub: object(expression: "HEAD:.github") { ...Entries }
+  docs: object(expression: "HEAD:docs") { ...Entries }
+"#;
+
+const ENTRIES_FRAGMENT: &str = "fragment Entries on GitObject { ... on Tree { entries { name } } }";
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+  /// repos that couldn't be loaded are null, with the reason in `errors`
+  data: Option<HashMap<String, Option<Repository>>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Repository {
+  name: String,
+  owner: Owner,
+  default_branch_ref: Option<Branch>,
+  is_private: bool,
+  homepage_url: Option<String>,
+  root: Option<Tree>,
+  dot_github: Option<Tree>,
+  docs: Option<Tree>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Owner {
+  /// `User` or `Organization`, the same as the REST API
+  #[serde(rename = "__typename")]
+  typename: String,
+  login: String,
+  avatar_url: Option<Url>,
+}
+
+#[derive(Deserialize)]
+struct Branch {
+  name: String,
+}
+
+#[derive(Deserialize)]
+struct Tree {
+  #[serde(default)]
+  entries: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+  name: String,
+}
+
+impl Repository {
+  fn has_readme(&self) -> bool {
+    [&self.root, &self.dot_github, &self.docs]
+      .into_iter()
+      .flatten()
+      .flat_map(|tree| &tree.entries)
+      .any(|entry| entry.name.to_lowercase().starts_with("readme"))
+  }
+}
+
+/// Load the metadata of many GitHub repos in one GraphQL query, filling the caches
+/// that [`Repo::load_with_client`] & the readme would otherwise make a request each for.
+/// Repos that fail to load are left for the REST API to retry
+pub(crate) async fn preload_repos(client: &RepoIconsClient, repos: &[(String, String)]) {
+  if repos.is_empty() {
+    return;
+  }
+
+  let repositories = match query_repos(client, repos).await {
+    Ok(repositories) => repositories,
+    Err(err) => {
+      warn!("failed to preload {} repos: {:?}", repos.len(), err);
+      return;
+    }
+  };
+
+  for (i, (owner, repo)) in repos.iter().enumerate() {
+    let repository = match repositories.get(&format!("repo{}", i)) {
+      Some(Some(repository)) => repository,
+      _ => continue,
+    };
+    let key = cache_key(owner, repo);
+
+    if !repository.has_readme() {
+      set_cached(
+        &client.caches().readmes,
+        key.clone(),
+        Err(RepoIconsError::NotFound { url: None }),
+      );
+    }
+
+    let is_org = repository.owner.typename == "Organization";
+    set_cached(
+      &client.caches().redirected_users,
+      key.clone(),
+      Ok((repository.owner.login.to_lowercase(), is_org)),
+    );
+
+    let repo = Repo {
+      owner: User {
+        login: repository.owner.login.clone(),
+        r#type: repository.owner.typename.clone(),
+        avatar_url: repository.owner.avatar_url.clone(),
+      },
+      name: repository.name.clone(),
+      // empty repos don't have a default branch
+      default_branch: repository
+        .default_branch_ref
+        .as_ref()
+        .map(|branch| branch.name.clone())
+        .unwrap_or_default(),
+      private: repository.is_private,
+      homepage: repository
+        .homepage_url
+        .as_deref()
+        .and_then(parse_homepage)
+        .filter(|homepage| !is_blacklisted_homepage(client.host(), homepage)),
+      avatar: None,
+    };
+    set_cached(&client.caches().repos, key, Ok(repo));
+  }
+}
+
+async fn query_repos(
+  client: &RepoIconsClient,
+  repos: &[(String, String)],
+) -> Result<HashMap<String, Option<Repository>>, RepoIconsError> {
+  let mut params = Vec::new();
+  let mut fields = Vec::new();
+  let mut variables = Map::new();
+
+  for (i, (owner, repo)) in repos.iter().enumerate() {
+    params.push(format!("$owner{}: String!, $name{}: String!", i, i));
+    fields.push(format!(
+      "repo{}: repository(owner: $owner{}, name: $name{}) {{ {} }}",
+      i, i, i, REPO_FIELDS
+    ));
+    variables.insert(format!("owner{}", i), Value::from(owner.as_str()));
+    variables.insert(format!("name{}", i), Value::from(repo.as_str()));
+  }
+
+  let body = json!({
+    "query": format!(
+      "query({}) {{ {} }}\n{}",
+      params.join(", "),
+      fields.join("\n"),
+      ENTRIES_FRAGMENT
+    ),
+    "variables": variables,
+  });
+
+  let url = client.host().graphql_url();
+  let start = Instant::now();
+
+  let response = async {
+    send(client, &url, || client.post(url.clone()).json(&body))
+      .await?
+      .json::<GraphqlResponse>()
+      .await
+      .map_err(RepoIconsError::from)
+  }
+  .await;
+
+  info!("{} ({} repos): {:?}", url, repos.len(), start.elapsed());
+
+  Ok(response?.data.unwrap_or_default())
+}
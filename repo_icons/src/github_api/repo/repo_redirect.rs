@@ -1,20 +1,18 @@
 use super::repo::{Repo, User};
-use cached::proc_macro::cached;
-use cached::SizedCache;
+use crate::{
+  github_api::{cache_key, get_or_load, send},
+  RepoIconsClient, RepoIconsError,
+};
 use instant::Instant;
-use serde::Deserialize;
-
-#[derive(Deserialize)]
-#[serde(untagged)]
-enum UserResponse {
-  Message { message: String },
-  User(User),
-}
 
 /// check if two repos are the same, following
 /// redirects (in case the user/repo was renamed)
 /// user/repo pairs should be transformed to lowercase!
-pub async fn is_same_repo(repo: (&str, &str), other_repo: (&str, &str)) -> bool {
+pub async fn is_same_repo(
+  client: &RepoIconsClient,
+  repo: (&str, &str),
+  other_repo: (&str, &str),
+) -> bool {
   let repo = (repo.0.to_lowercase(), repo.1.to_lowercase());
   let other_repo = (other_repo.0.to_lowercase(), repo.1.to_lowercase());
 
@@ -28,7 +26,7 @@ pub async fn is_same_repo(repo: (&str, &str), other_repo: (&str, &str)) -> bool
     return false;
   }
 
-  let other_repo_res = get_redirected_repo(&other_repo.0, &other_repo.1)
+  let other_repo_res = get_redirected_repo(client, &other_repo.0, &other_repo.1)
     .await
     .map(|(owner, repo, _)| (owner, repo));
 
@@ -38,7 +36,7 @@ pub async fn is_same_repo(repo: (&str, &str), other_repo: (&str, &str)) -> bool
     return true;
   }
 
-  let repo_res = get_redirected_repo(&repo.0, &repo.1)
+  let repo_res = get_redirected_repo(client, &repo.0, &repo.1)
     .await
     .map(|(owner, repo, _)| (owner, repo));
   let repo = repo_res.unwrap_or(repo);
@@ -50,41 +48,54 @@ pub async fn is_same_repo(repo: (&str, &str), other_repo: (&str, &str)) -> bool
   false
 }
 
-#[cached(
-  sync_writes = true,
-  type = "SizedCache<String, Result<(String, bool), String>>",
-  create = "{ SizedCache::with_size(100) }",
-  convert = r#"{ format!("{}/{}", owner.to_lowercase(), repo.to_lowercase()) }"#
-)]
-pub async fn get_redirected_user(owner: &str, repo: &str) -> Result<(String, bool), String> {
-  match get_redirected_repo(owner, repo).await {
+pub async fn get_redirected_user(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<(String, bool), RepoIconsError> {
+  get_or_load(
+    &client.caches().redirected_users,
+    cache_key(owner, repo),
+    load_redirected_user(client, owner, repo),
+  )
+  .await
+}
+
+async fn load_redirected_user(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<(String, bool), RepoIconsError> {
+  match get_redirected_repo(client, owner, repo).await {
     Ok((owner, _, is_org)) => Ok((owner, is_org)),
     Err(_) => {
-      let url = format!("users/{}", owner);
+      let path = format!("users/{}", owner);
+      let url = client.host().api_url(&path);
       let start = Instant::now();
 
       let user = async {
-        gh_api_get!("{}", url)
-          .send()
+        send(client, &url, || client.api_get(&path))
           .await?
-          .json::<UserResponse>()
+          .json::<User>()
           .await
+          .map_err(RepoIconsError::from)
       }
-      .await
-      .map_err(|e| format!("{}: {:?}", url, e));
+      .await;
 
       info!("{}: {:?}", url, start.elapsed());
 
-      match user? {
-        UserResponse::Message { message } => Err(message),
-        UserResponse::User(user) => Ok((owner.to_lowercase(), user.r#type == "Organization")),
-      }
+      let user = user?;
+      Ok((owner.to_lowercase(), user.r#type == "Organization"))
     }
   }
 }
 
-async fn get_redirected_repo(owner: &str, repo: &str) -> Result<(String, String, bool), String> {
-  let repo = Repo::load(owner, repo).await?;
+async fn get_redirected_repo(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+) -> Result<(String, String, bool), RepoIconsError> {
+  let repo = Repo::load_with_client(client, owner, repo).await?;
 
   Ok((
     repo.owner.login.to_lowercase(),
@@ -1,15 +1,14 @@
 mod repo_files;
 
+pub(crate) use repo_files::{get_repo_files, File, FileType};
+
 use crate::blacklist::is_badge_text;
-use crate::RepoFile;
+use crate::github_api::send;
+use crate::{RejectedCandidate, RepoFile, RepoIconsClient, RepoIconsError, Score};
 use fancy_regex::{escape, Regex};
 use futures::future::join_all;
-use itertools::Itertools;
-use repo_files::{get_repo_files, File, FileType};
-use std::convert::TryInto;
-use std::error::Error;
+use std::collections::HashSet;
 use std::path::Path;
-use vec1::Vec1;
 
 const OWNER_PREFIXES: [&str; 2] = ["get", "with"];
 const OWNER_SUFFIXES: [&str; 7] = ["js", "rs", "io", "land", "pkg", "hq", "app"];
@@ -41,23 +40,27 @@ fn is_valid_blob(file: &File) -> bool {
     && (file.path.ends_with(".png") || file.path.ends_with(".ico") || file.path.ends_with(".svg"))
 }
 
-fn get_weight(owner: &str, repo: &str, file: &File) -> u8 {
+fn get_weight(owner: &str, repo: &str, subpath: Option<&str>, file: &File) -> Score {
   let owner = stripped_owner_lowercase(owner);
   let repo = repo.to_lowercase();
 
-  let fullpath = file.path.to_lowercase();
-  let (path, filename) = get_path_and_filename(&fullpath);
+  let lowercase_path = file.path.to_lowercase();
+  // inside of the subpath, the rules only look at the path relative to it
+  let relative_path = subpath
+    .and_then(|subpath| lowercase_path.strip_prefix(&format!("{}/", subpath.to_lowercase())));
+  let fullpath = relative_path.unwrap_or(&lowercase_path);
+  let (path, filename) = get_path_and_filename(fullpath);
 
-  let mut weight = 0;
+  let mut score = Score::default();
   let mut matches_icon = false;
 
   if is_badge_text(&fullpath) || filename.contains("issue") || path.contains("setup") {
-    return 0;
+    return score;
   }
 
   if filename.contains(&repo) {
     matches_icon = true;
-    weight += 2;
+    score.add("filename contains repo name", 2);
   }
 
   let exactly_repo_name = Regex::new(&format!(
@@ -66,7 +69,7 @@ fn get_weight(owner: &str, repo: &str, file: &File) -> u8 {
   ))
   .unwrap();
   if exactly_repo_name.is_match(&filename).unwrap() {
-    weight += 2;
+    score.add("filename is exactly repo name", 2);
   }
 
   let ignore_paths = regex!(
@@ -75,61 +78,73 @@ fn get_weight(owner: &str, repo: &str, file: &File) -> u8 {
   if !ignore_paths.is_match(&fullpath).unwrap() {
     if filename.contains(&owner) {
       matches_icon = true;
-      weight += 1;
+      score.add("filename contains owner name", 1);
     }
 
     let is_favicon = filename.contains("favicon");
     if is_favicon {
       matches_icon = true;
-      weight += 2;
+      score.add("favicon", 2);
     }
 
     let app_icon = regex!("(app(?!le).*icon)|(icon.*app(?!le))");
     if app_icon.is_match(filename).unwrap() {
       matches_icon = true;
-      weight += 2;
+      score.add("app icon", 2);
     }
 
     let logo = regex!("logo(?!ut|n|s)");
     if logo.is_match(&fullpath).unwrap() {
       matches_icon = true;
-      weight += 1;
+      score.add("path contains logo", 1);
 
       let exactly_logo = regex!(r"^(logo|icon)([-_].+)?\.[^.]+$");
       if exactly_logo.is_match(&filename).unwrap() {
-        weight += 2;
+        score.add("filename is exactly logo / icon", 2);
       }
     }
 
     if matches_icon {
       let public = regex!("(public|static|resources|assets|media|www|xcassets|appiconset)/");
       if public.is_match(&fullpath).unwrap() {
-        weight += 1;
+        score.add("inside public directory", 1);
       }
 
       let directly_in_images = regex!("(images|img|public|static|resources|assets|media|www)$");
       if directly_in_images.is_match(&path).unwrap() {
-        weight += 2;
+        score.add("directly inside images directory", 2);
         if is_favicon {
-          weight += 1;
+          score.add("favicon directly inside images directory", 1);
         }
       }
 
       if path.contains("server") || fullpath.contains("website") {
-        weight += 1;
+        score.add("inside server / website directory", 1);
       }
     }
   }
 
-  weight
+  if relative_path.is_some() && score.weight > 0 {
+    score.add("inside subpath", 3);
+  }
+
+  score
 }
 
 async fn get_package_json_icon(
+  client: &RepoIconsClient,
   owner: &str,
   repo: &str,
   commit_sha: &str,
+  subpath: Option<&str>,
   files: &Vec<File>,
 ) -> Option<File> {
+  let is_subpath = |path: &str| {
+    subpath
+      .map(|subpath| path.eq_ignore_ascii_case(subpath))
+      .unwrap_or(false)
+  };
+
   let package_json_icons: Vec<(bool, File)> = join_all(
     files
       .iter()
@@ -137,7 +152,7 @@ async fn get_package_json_icon(
         let (path, filename) = get_path_and_filename(&file.path);
 
         matches!(file.r#type, FileType::Blob)
-          && path == ""
+          && (path == "" || is_subpath(path))
           && filename == "package.json"
           && files.iter().any(|file| {
             (path == "" || file.path.starts_with(&format!("{}/", path))) && is_valid_blob(file)
@@ -149,19 +164,16 @@ async fn get_package_json_icon(
           icon: String,
         }
 
-        let package_json = gh_get!(
-          "https://raw.githubusercontent.com/{}/{}/{}/{}",
-          owner,
-          repo,
-          commit_sha,
-          file.path
-        )
-        .send()
-        .await
-        .ok()?
-        .json::<PackageJSON>()
-        .await
-        .ok()?;
+        let url = client
+          .host()
+          .file_url(&format!("{}/{}", owner, repo), commit_sha, &file.path);
+
+        let package_json = send(client, &url, || client.get(url.clone()))
+          .await
+          .ok()?
+          .json::<PackageJSON>()
+          .await
+          .ok()?;
 
         let (path, _) = get_path_and_filename(&file.path);
 
@@ -174,7 +186,7 @@ async fn get_package_json_icon(
               .unwrap()
         })?;
 
-        Some((path == "", icon_file))
+        Some((is_subpath(path), icon_file))
       }),
   )
   .await
@@ -182,13 +194,14 @@ async fn get_package_json_icon(
   .filter_map(|icon_file| icon_file)
   .collect();
 
+  // the package.json of the subpath is preferred over the root one
   if package_json_icons.len() > 0 {
     let first = package_json_icons[0].1.clone();
 
     Some(
       package_json_icons
         .into_iter()
-        .find_map(|(is_root, file)| if is_root { Some(file) } else { None })
+        .find_map(|(is_subpath, file)| if is_subpath { Some(file) } else { None })
         .unwrap_or(first),
     )
   } else {
@@ -196,55 +209,95 @@ async fn get_package_json_icon(
   }
 }
 
+/// The icon files picked out of the repo
+pub struct RepoIconFiles {
+  /// whether the file came from the `icon` field of a package.json
+  pub is_icon_field: bool,
+  /// empty when none of the files look like an icon
+  pub files: Vec<(RepoFile, Score)>,
+  /// only collected when explaining, as every file in the repo is a candidate
+  pub rejected: Vec<RejectedCandidate>,
+}
+
 pub async fn get_repo_icon_files(
+  client: &RepoIconsClient,
   owner: &str,
   repo: &str,
-) -> Result<Option<(bool, Vec1<RepoFile>)>, Box<dyn Error>> {
-  let (commit_sha, files) = get_repo_files(owner, repo).await?;
-
-  let result = if let Some(result) = get_package_json_icon(owner, repo, &commit_sha, &files).await {
-    Some((true, Vec1::new(result)))
-  } else {
-    let mut results = files
-      .into_iter()
-      .filter(|file| is_valid_blob(file))
-      .map(|file| {
-        let weight = get_weight(owner, repo, &file);
-        (file, weight)
-      })
-      .filter(|(_, weight)| *weight > 0)
-      .collect::<Vec<_>>();
+  git_ref: Option<&str>,
+  subpath: Option<&str>,
+  explain: bool,
+) -> Result<RepoIconFiles, RepoIconsError> {
+  let (commit_sha, files) = get_repo_files(client, owner, repo, git_ref).await?;
+
+  let repo_file = |file: File| RepoFile {
+    github: format!("{}/{}", owner, repo),
+    commit_sha: commit_sha.clone(),
+
+    sha: file.sha,
+    path: file.path,
+  };
 
-    results.sort_by(|(_, a_weight), (_, b_weight)| b_weight.cmp(&a_weight));
+  if let Some(file) = get_package_json_icon(client, owner, repo, &commit_sha, subpath, &files).await
+  {
+    let mut score = Score::default();
+    score.add("package.json icon field", 0);
 
-    results.get(0).cloned().map(|(_, first_weight)| {
-      let final_results = results
-        .into_iter()
-        .filter_map(|(file, other_weight)| (first_weight == other_weight).then_some(file))
-        .unique_by(|file| file.sha.clone())
-        .collect::<Vec<_>>();
+    return Ok(RepoIconFiles {
+      is_icon_field: true,
+      files: vec![(repo_file(file), score)],
+      rejected: Vec::new(),
+    });
+  }
 
-      (false, final_results.try_into().unwrap())
+  let mut results = files
+    .into_iter()
+    .filter(|file| is_valid_blob(file))
+    .map(|file| {
+      let score = get_weight(owner, repo, subpath, &file);
+      (file, score)
     })
-  };
+    .collect::<Vec<_>>();
 
-  Ok(result.map(|(is_package_json, files)| {
-    (
-      is_package_json,
-      files
-        .into_iter()
-        .map(|file| RepoFile {
-          github: format!("{}/{}", owner, repo),
-          commit_sha: commit_sha.clone(),
-
-          sha: file.sha,
-          path: file.path,
-        })
-        .collect::<Vec<_>>()
-        .try_into()
-        .unwrap(),
-    )
-  }))
+  results.sort_by(|(_, a), (_, b)| b.weight.cmp(&a.weight));
+
+  // files that no rules matched are never picked, even when there's nothing else
+  let first_weight = results.first().map_or(0, |(_, score)| score.weight).max(1);
+
+  let mut picked = Vec::new();
+  let mut rejected = Vec::new();
+  let mut seen_shas = HashSet::new();
+
+  for (file, score) in results {
+    if !explain && score.weight < first_weight {
+      // sorted by weight, so none of the rest can be picked
+      break;
+    }
+
+    let reason = if score.weight == 0 {
+      "no rules matched".to_string()
+    } else if score.weight < first_weight {
+      format!("outranked by weight {}", first_weight)
+    } else if matches!(&file.sha, Some(sha) if !seen_shas.insert(sha.clone())) {
+      "same contents as a picked file".to_string()
+    } else {
+      picked.push((repo_file(file), score));
+      continue;
+    };
+
+    if explain {
+      rejected.push(RejectedCandidate {
+        candidate: file.path,
+        score,
+        reason,
+      });
+    }
+  }
+
+  Ok(RepoIconFiles {
+    is_icon_field: false,
+    files: picked,
+    rejected,
+  })
 }
 
 fn get_path_and_filename(fullpath: &str) -> (&str, &str) {
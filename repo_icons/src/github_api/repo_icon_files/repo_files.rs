@@ -1,8 +1,20 @@
-use cached::proc_macro::cached;
-use cached::SizedCache;
+use crate::{
+  forges::{bitbucket, encode, gitea, gitlab, hugging_face, Forge},
+  github_api::{cache_key, get_or_revalidate, send_if_modified, Revalidated},
+  RepoIconsClient, RepoIconsError,
+};
+use futures::future::join_all;
 use instant::Instant;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-#[derive(Debug, Clone, Deserialize)]
+/// How many subtrees to list when the recursive tree was truncated
+const MAX_SUBTREE_REQUESTS: usize = 25;
+
+/// How many subtrees to list at once
+const SUBTREE_CONCURRENCY: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum FileType {
   Blob,
@@ -10,44 +22,293 @@ pub enum FileType {
   Commit,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct File {
   pub path: String,
   pub r#type: FileType,
-  pub sha: String,
+  /// missing on forges whose listings don't include blob shas
+  #[serde(alias = "id")]
+  pub sha: Option<String>,
 }
 
 #[derive(Deserialize)]
-#[serde(untagged)]
-enum TreesResponse {
-  Trees { sha: String, tree: Vec<File> },
-  Message { message: String },
-}
-
-#[cached(
-  sync_writes = true,
-  type = "SizedCache<String, Result<(String, Vec<File>), String>>",
-  create = "{ SizedCache::with_size(100) }",
-  convert = r#"{ format!("{}/{}", owner.to_lowercase(), repo.to_lowercase()) }"#
-)]
-pub async fn get_repo_files(owner: &str, repo: &str) -> Result<(String, Vec<File>), String> {
-  let url = format!("repos/{}/{}/git/trees/HEAD?recursive=1", owner, repo);
+struct Trees {
+  sha: String,
+  tree: Vec<File>,
+  /// set when the tree has too many entries to be listed in full
+  #[serde(default)]
+  truncated: bool,
+}
+
+/// The commit sha & files of the repo, at the given branch, tag
+/// or commit. Defaults to the default branch
+pub async fn get_repo_files(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+) -> Result<(String, Vec<File>), RepoIconsError> {
+  let key = match git_ref {
+    Some(git_ref) => format!("{}@{}", cache_key(owner, repo), git_ref),
+    None => cache_key(owner, repo),
+  };
+
+  get_or_revalidate(&client.caches().repo_files, key, |etag| async move {
+    let files = match client.host().forge() {
+      Forge::Github => return load_repo_files(client, owner, repo, git_ref, etag.as_deref()).await,
+      Forge::Gitlab => gitlab::get_repo_files(client, owner, repo, git_ref).await?,
+      Forge::Gitea => gitea::get_repo_files(client, owner, repo, git_ref).await?,
+      Forge::Bitbucket => bitbucket::get_repo_files(client, owner, repo, git_ref).await?,
+      Forge::HuggingFace => hugging_face::get_repo_files(client, owner, repo, git_ref).await?,
+    };
+
+    Ok(Revalidated::Modified(files, None))
+  })
+  .await
+}
+
+async fn load_repo_files(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  git_ref: Option<&str>,
+  etag: Option<&str>,
+) -> Result<Revalidated<(String, Vec<File>)>, RepoIconsError> {
+  // the subtrees of an unchanged tree are unchanged too
+  let trees = get_tree(client, owner, repo, git_ref.unwrap_or("HEAD"), true, etag).await?;
+  let (trees, etag) = match trees {
+    Revalidated::Modified(trees, etag) => (trees, etag),
+    Revalidated::NotModified => return Ok(Revalidated::NotModified),
+  };
+  let Trees {
+    sha,
+    mut tree,
+    truncated,
+  } = trees;
+
+  if truncated {
+    walk_subtrees(client, owner, repo, &mut tree).await;
+  }
+
+  Ok(Revalidated::Modified((sha, tree), etag))
+}
+
+async fn get_tree(
+  client: &RepoIconsClient,
+  owner: &str,
+  repo: &str,
+  tree: &str,
+  recursive: bool,
+  etag: Option<&str>,
+) -> Result<Revalidated<Trees>, RepoIconsError> {
+  let mut path = format!("repos/{}/{}/git/trees/{}", owner, repo, encode(tree));
+  if recursive {
+    path.push_str("?recursive=1");
+  }
+
+  let url = client.host().api_url(&path);
   let start = Instant::now();
 
   let res = async {
-    gh_api_get!("{}", url)
-      .send()
-      .await?
-      .json::<TreesResponse>()
-      .await
+    match send_if_modified(client, &url, etag, || client.api_get(&path)).await? {
+      Revalidated::Modified(res, etag) => {
+        Ok(Revalidated::Modified(res.json::<Trees>().await?, etag))
+      }
+      Revalidated::NotModified => Ok(Revalidated::NotModified),
+    }
   }
-  .await
-  .map_err(|e| format!("{}: {:?}", url, e).to_string());
+  .await;
 
   info!("{}: {:?}", url, start.elapsed());
 
-  match res? {
-    TreesResponse::Trees { sha, tree } => Ok((sha, tree)),
-    TreesResponse::Message { message } => Err(message),
+  res
+}
+
+/// Huge repos only get part of their recursive tree, which can miss the logo.
+/// Fill in the rest by listing the directories it didn't get to one level at
+/// a time, starting with the ones icons are usually found in
+async fn walk_subtrees(client: &RepoIconsClient, owner: &str, repo: &str, files: &mut Vec<File>) {
+  let mut known = files
+    .iter()
+    .map(|file| file.path.clone())
+    .collect::<HashSet<_>>();
+
+  // directories with entries in the truncated tree have been listed already
+  let listed = files
+    .iter()
+    .filter_map(|file| file.path.rsplit_once('/').map(|(dir, _)| dir.to_string()))
+    .collect::<HashSet<_>>();
+
+  let mut pending = files
+    .iter()
+    .filter(|file| {
+      matches!(file.r#type, FileType::Tree)
+        && !listed.contains(&file.path)
+        && !is_ignored_directory(&file.path)
+    })
+    .filter_map(|file| Some((file.path.clone(), file.sha.clone()?)))
+    .collect::<Vec<_>>();
+  let mut requests = 0;
+
+  while !pending.is_empty() && requests < MAX_SUBTREE_REQUESTS {
+    pending.sort_by_key(|(path, _)| directory_priority(path));
+
+    let count = SUBTREE_CONCURRENCY
+      .min(MAX_SUBTREE_REQUESTS - requests)
+      .min(pending.len());
+    let batch = pending.split_off(pending.len() - count);
+    requests += count;
+
+    let trees = join_all(
+      batch
+        .iter()
+        .map(|(_, sha)| get_tree(client, owner, repo, sha, false, None)),
+    )
+    .await;
+
+    for ((dir, _), trees) in batch.into_iter().zip(trees) {
+      let tree = match trees.and_then(Revalidated::modified) {
+        Ok(trees) => trees.tree,
+        Err(err) => {
+          warn!("{}/{}: failed to list {}: {:?}", owner, repo, dir, err);
+          continue;
+        }
+      };
+
+      for mut file in tree {
+        file.path = format!("{}/{}", dir, file.path);
+
+        if let (FileType::Tree, Some(sha)) = (&file.r#type, &file.sha) {
+          if !is_ignored_directory(&file.path) {
+            pending.push((file.path.clone(), sha.clone()));
+          }
+        }
+
+        if known.insert(file.path.clone()) {
+          files.push(file);
+        }
+      }
+    }
+  }
+}
+
+/// Directories that [`get_weight`](super::get_weight) scores highly are listed first,
+/// then the shallowest ones, as they can lead to them (`packages/app/public`)
+fn directory_priority(path: &str) -> (bool, isize) {
+  let path = path.to_lowercase();
+  let favoured = regex!(
+    r"(^|/)(public|static|assets|resources|media|www|images|img|\.github|website|docs)(/|$)"
+  )
+  .is_match(&path)
+  .unwrap();
+
+  (favoured, -(path.matches('/').count() as isize))
+}
+
+/// Directories that can't contain the icon of the repo
+fn is_ignored_directory(path: &str) -> bool {
+  regex!(
+    r"(^|/)(node_modules|vendor|third[-_]party|e2e|fixtures|examples?|tests?|__tests__|icons|themes)(/|$)"
+  )
+  .is_match(&path.to_lowercase())
+  .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{github_api::get_repo_icon_files, BoxFuture, GithubBackend, MaybeSendFutureExt};
+  use reqwest::{Client, Request, Response};
+  use serde_json::{json, Value};
+  use std::sync::{Arc, Mutex};
+
+  /// Serves a truncated recursive tree with 30 unlisted packages & the docs,
+  /// whose logo is two levels down. Records the subtrees that were listed
+  struct TruncatedTree {
+    listed: Arc<Mutex<Vec<String>>>,
+  }
+
+  fn entry(path: &str, r#type: &str) -> Value {
+    json!({ "path": path, "type": r#type, "sha": format!("{}-sha", path.replace('/', "-")) })
+  }
+
+  impl TruncatedTree {
+    fn tree(&self, sha: &str) -> Value {
+      let entries = match sha {
+        "HEAD" => {
+          let mut entries = vec![
+            entry("README.md", "blob"),
+            entry("docs", "tree"),
+            entry("src", "tree"),
+            entry("src/main.rs", "blob"),
+            entry("tests", "tree"),
+          ];
+          entries.extend((0..30).map(|i| entry(&format!("pkg{:02}", i), "tree")));
+          return json!({ "sha": "root-sha", "tree": entries, "truncated": true });
+        }
+        "docs-sha" => vec![entry("public", "tree")],
+        "docs-public-sha" => vec![entry("logo.svg", "blob")],
+        _ => vec![entry("index.js", "blob")],
+      };
+
+      json!({ "sha": sha, "tree": entries, "truncated": false })
+    }
+  }
+
+  impl GithubBackend for TruncatedTree {
+    fn execute<'a>(
+      &'a self,
+      _http: &'a Client,
+      request: Request,
+    ) -> BoxFuture<'a, Result<Response, RepoIconsError>> {
+      async move {
+        let sha = request.url().path().rsplit('/').next().unwrap().to_string();
+        if request.url().query().is_none() {
+          self.listed.lock().unwrap().push(sha.clone());
+        }
+
+        let body = self.tree(&sha).to_string();
+        Ok(
+          http::Response::builder()
+            .status(200)
+            .body(body)
+            .unwrap()
+            .into(),
+        )
+      }
+      .boxed_maybe_send()
+    }
+  }
+
+  #[tokio::test]
+  async fn truncated_trees_are_walked_from_their_unlisted_directories() {
+    let listed = Arc::new(Mutex::new(Vec::new()));
+    let client = RepoIconsClient::builder()
+      .token(None::<String>)
+      .backend(TruncatedTree {
+        listed: listed.clone(),
+      })
+      .build()
+      .unwrap();
+
+    let icon_files = get_repo_icon_files(&client, "example", "huge", None, None, false)
+      .await
+      .unwrap();
+    let paths = icon_files
+      .files
+      .iter()
+      .map(|(file, _)| file.path.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(paths, ["docs/public/logo.svg"]);
+
+    let listed = listed.lock().unwrap();
+    assert_eq!(listed.len(), MAX_SUBTREE_REQUESTS);
+    for sha in ["docs-sha", "docs-public-sha"] {
+      assert!(listed.iter().any(|listed| listed == sha), "{}", sha);
+    }
+    // the root, the directories it listed & the ignored ones aren't listed again
+    for sha in ["HEAD", "root-sha", "src-sha", "tests-sha"] {
+      assert!(!listed.iter().any(|listed| listed == sha), "{}", sha);
+    }
   }
 }
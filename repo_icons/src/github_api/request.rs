@@ -0,0 +1,203 @@
+use crate::{RepoIconsClient, RepoIconsError};
+use futures_timer::Delay;
+use instant::{Duration, SystemTime};
+use reqwest::{header::IF_NONE_MATCH, RequestBuilder, Response, StatusCode};
+use std::{
+  collections::hash_map::RandomState,
+  hash::{BuildHasher, Hasher},
+};
+use url::Url;
+
+/// The remaining GitHub API budget, as of the last response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+  pub remaining: u32,
+  /// unix timestamp (in seconds) of when the budget resets
+  pub reset: u64,
+}
+
+impl RateLimit {
+  pub fn is_exhausted(&self) -> bool {
+    self.remaining == 0 && self.reset > now()
+  }
+}
+
+/// How requests that failed with a transient error get retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+  pub max_retries: u32,
+  /// Delay before the first retry, doubled for every retry after it
+  pub base_delay: Duration,
+  /// Never wait longer than this between retries. Secondary rate limits
+  /// that ask for a longer wait fail straight away instead
+  pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self {
+      max_retries: 3,
+      base_delay: Duration::from_millis(500),
+      max_delay: Duration::from_secs(10),
+    }
+  }
+}
+
+/// The remaining GitHub API budget of the global client
+pub fn rate_limit() -> Option<RateLimit> {
+  RepoIconsClient::global().rate_limit()
+}
+
+/// The response to a request that may have been conditional
+pub(crate) enum Revalidated<T> {
+  /// Along with the ETag of the response
+  Modified(T, Option<String>),
+  /// The cached response is still current
+  NotModified,
+}
+
+impl<T> Revalidated<T> {
+  /// The response to a request that was made without an ETag
+  pub(crate) fn modified(self) -> Result<T, RepoIconsError> {
+    match self {
+      Revalidated::Modified(value, _) => Ok(value),
+      Revalidated::NotModified => Err(RepoIconsError::Other {
+        message: "not modified, without a cached response".to_string(),
+      }),
+    }
+  }
+}
+
+/// Send a request, turning unsuccessful responses into a [`RepoIconsError`].
+///
+/// Server errors & secondary rate limits are retried with a jittered backoff,
+/// and requests to the GitHub API fail fast once the rate limit budget is gone
+pub async fn send<F: Fn() -> RequestBuilder>(
+  client: &RepoIconsClient,
+  url: &Url,
+  request: F,
+) -> Result<Response, RepoIconsError> {
+  let is_api = client.host().is_api_url(url);
+
+  if is_api {
+    if let Some(rate_limit) = client.rate_limit().filter(RateLimit::is_exhausted) {
+      return Err(RepoIconsError::RateLimited {
+        url: Some(url.clone()),
+        reset: Some(rate_limit.reset),
+      });
+    }
+  }
+
+  let policy = client.retry_policy();
+  let mut attempt = 0;
+
+  loop {
+    let delay = backoff(policy, attempt);
+    attempt += 1;
+
+    let request = request().build()?;
+    let res = match client.backend().execute(client.http(), request).await {
+      Ok(res) => res,
+      Err(err @ RepoIconsError::Network { .. }) if attempt <= policy.max_retries => {
+        warn!("{}: {}, retrying in {:?}", url, err, delay);
+        Delay::new(delay).await;
+        continue;
+      }
+      Err(err) => return Err(err),
+    };
+
+    if is_api {
+      update_rate_limit(client, &res);
+    }
+
+    let status = res.status();
+    if status.is_success() || status == StatusCode::NOT_MODIFIED {
+      return Ok(res);
+    }
+
+    let retry_after = header(&res, "retry-after")
+      .and_then(|secs| secs.parse().ok())
+      .map(Duration::from_secs);
+
+    let error = RepoIconsError::from_response(url, res).await;
+
+    let delay = match (&error, retry_after) {
+      // secondary rate limits tell us how long to wait
+      (RepoIconsError::RateLimited { .. }, Some(retry_after)) => retry_after,
+      _ if status.is_server_error() => delay,
+      _ => return Err(error),
+    };
+
+    if attempt > policy.max_retries || delay > policy.max_delay {
+      return Err(error);
+    }
+
+    warn!("{}: {}, retrying in {:?}", url, error, delay);
+    Delay::new(delay).await;
+  }
+}
+
+/// Send a request with the ETag of a cached response, if there is one.
+/// GitHub doesn't count `304 Not Modified` responses against the rate limit
+pub(crate) async fn send_if_modified<F: Fn() -> RequestBuilder>(
+  client: &RepoIconsClient,
+  url: &Url,
+  etag: Option<&str>,
+  request: F,
+) -> Result<Revalidated<Response>, RepoIconsError> {
+  let res = send(client, url, || match etag {
+    Some(etag) => request().header(IF_NONE_MATCH, etag),
+    None => request(),
+  })
+  .await?;
+
+  if res.status() == StatusCode::NOT_MODIFIED {
+    return Ok(Revalidated::NotModified);
+  }
+
+  let etag = header(&res, "etag");
+  Ok(Revalidated::Modified(res, etag))
+}
+
+fn update_rate_limit(client: &RepoIconsClient, res: &Response) {
+  // GraphQL has its own budget, separate from the REST API
+  if header(res, "x-ratelimit-resource").as_deref() == Some("graphql") {
+    return;
+  }
+
+  // GitLab uses the IETF draft names, without the x- prefix
+  let header = |name: &str| header(res, &format!("x-{}", name)).or_else(|| header(res, name));
+  let remaining = header("ratelimit-remaining").and_then(|value| value.parse().ok());
+  let reset = header("ratelimit-reset").and_then(|value| value.parse().ok());
+
+  if let (Some(remaining), Some(reset)) = (remaining, reset) {
+    client.set_rate_limit(RateLimit { remaining, reset });
+  }
+}
+
+pub(crate) fn header(res: &Response, name: &str) -> Option<String> {
+  res
+    .headers()
+    .get(name)
+    .and_then(|value| value.to_str().ok())
+    .map(|value| value.to_string())
+}
+
+/// Exponential backoff, with up to 50% of jitter so that
+/// concurrent requests don't all retry at the same time
+fn backoff(policy: &RetryPolicy, attempt: u32) -> Duration {
+  let delay = policy
+    .base_delay
+    .saturating_mul(2u32.saturating_pow(attempt))
+    .min(policy.max_delay);
+
+  let jitter = RandomState::new().build_hasher().finish() % 1000;
+  delay.mul_f64(0.5 + jitter as f64 / 2000.0)
+}
+
+pub(crate) fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or(0)
+}
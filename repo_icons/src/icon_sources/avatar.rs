@@ -0,0 +1,40 @@
+use super::{IconSource, IconSourceContext};
+use crate::{BoxFuture, Forge, MaybeSendFutureExt, RepoIcon, RepoIconRank, RepoIconsError};
+
+/// The avatar of the repo owner, or of the repo itself on forges that have them
+pub struct AvatarSource;
+
+impl AvatarSource {
+  pub const NAME: &'static str = "avatar";
+}
+
+impl IconSource for AvatarSource {
+  fn name(&self) -> &str {
+    Self::NAME
+  }
+
+  fn ranks(&self) -> &[RepoIconRank] {
+    &[
+      RepoIconRank::Avatar,
+      RepoIconRank::OrgAvatar,
+      RepoIconRank::UserAvatarFallback,
+    ]
+  }
+
+  fn load<'a>(
+    &'a self,
+    ctx: &'a IconSourceContext<'a>,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+    async move {
+      let icon = match ctx.client.host().forge() {
+        Forge::Github => {
+          Some(RepoIcon::load_user_avatar_with_client(ctx.client, ctx.owner, ctx.repo).await?)
+        }
+        _ => RepoIcon::load_repo_avatar(ctx.client, ctx.owner, ctx.repo).await?,
+      };
+
+      Ok(icon.into_iter().collect())
+    }
+    .boxed_maybe_send()
+  }
+}
@@ -0,0 +1,59 @@
+use super::{IconSource, IconSourceContext};
+use crate::{
+  github_api::Repo, BoxFuture, MaybeSendFutureExt, RepoIcon, RepoIconRank, RepoIconsError,
+};
+
+/// Icons scraped from the homepage set on the repo
+pub struct HomepageSource;
+
+impl HomepageSource {
+  pub const NAME: &'static str = "homepage";
+}
+
+impl IconSource for HomepageSource {
+  fn name(&self) -> &str {
+    Self::NAME
+  }
+
+  fn ranks(&self) -> &[RepoIconRank] {
+    &[
+      RepoIconRank::AppIcon,
+      RepoIconRank::SiteFavicon,
+      RepoIconRank::SiteLogo,
+    ]
+  }
+
+  fn load<'a>(
+    &'a self,
+    ctx: &'a IconSourceContext<'a>,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+    async move {
+      let homepage = match Repo::load_with_client(ctx.client, ctx.owner, ctx.repo).await? {
+        Repo {
+          homepage: Some(homepage),
+          ..
+        } => homepage,
+        _ => return Ok(Vec::new()),
+      };
+
+      let icons = ctx
+        .client
+        .backend()
+        .homepage_icons(
+          ctx.client.http(),
+          ctx.client.host(),
+          &homepage,
+          ctx.best_matches_only,
+        )
+        .await?;
+
+      Ok(
+        icons
+          .into_iter()
+          .map(|icon| icon.with_client(ctx.client))
+          .collect(),
+      )
+    }
+    .boxed_maybe_send()
+  }
+}
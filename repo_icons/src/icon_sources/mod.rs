@@ -0,0 +1,168 @@
+mod avatar;
+mod homepage;
+mod readme;
+mod repo_files;
+mod space_emoji;
+
+pub use avatar::*;
+pub use homepage::*;
+pub use readme::*;
+pub use repo_files::*;
+pub use space_emoji::*;
+
+use crate::{
+  BoxFuture, MaybeSend, MaybeSync, RejectedCandidate, RepoIcon, RepoIconRank, RepoIconsClient,
+  RepoIconsError,
+};
+use std::{mem, sync::Mutex};
+
+/// Everything a source needs to know about the repo being loaded
+pub struct IconSourceContext<'a> {
+  /// The client to make GitHub requests with
+  pub client: &'a RepoIconsClient,
+  pub owner: &'a str,
+  pub repo: &'a str,
+  /// The package inside of a monorepo to focus on, see
+  /// [`RepoIconsOptions::subpath`](crate::RepoIconsOptions::subpath)
+  pub subpath: Option<&'a str>,
+  /// The branch, tag or commit to load files from, see
+  /// [`RepoIconsOptions::git_ref`](crate::RepoIconsOptions::git_ref)
+  pub git_ref: Option<&'a str>,
+  pub best_matches_only: bool,
+  /// Whether to attach an [`Explanation`](crate::Explanation) to the icons
+  /// & [`reject`](Self::reject) the candidates that weren't picked
+  pub explain: bool,
+  pub(crate) rejected: Mutex<Vec<RejectedCandidate>>,
+}
+
+impl IconSourceContext<'_> {
+  /// Record a candidate that the source considered but didn't pick, which is
+  /// reported once for the whole load rather than on each icon
+  pub fn reject(&self, candidate: RejectedCandidate) {
+    self.rejected.lock().unwrap().push(candidate);
+  }
+
+  pub(crate) fn take_rejected(&self) -> Vec<RejectedCandidate> {
+    mem::take(&mut *self.rejected.lock().unwrap())
+  }
+}
+
+/// A place icons for a repo can be found. Implement this to plug your
+/// own sources into [`RepoIconsOptions::source`](crate::RepoIconsOptions::source)
+///
+/// ```
+/// struct Registry;
+///
+/// impl IconSource for Registry {
+///   fn name(&self) -> &str {
+///     "registry"
+///   }
+///
+///   fn load<'a>(
+///     &'a self,
+///     ctx: &'a IconSourceContext<'a>,
+///   ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+///     async move {
+///       let url = format!("https://assets.internal/{}/{}.png", ctx.owner, ctx.repo);
+///       Ok(vec![RepoIcon::load(url, RepoIconKind::Avatar).await?])
+///     }
+///     .boxed_maybe_send()
+///   }
+/// }
+/// ```
+///
+/// Sources are `Send` & `Sync` on native targets, so that loads can run on
+/// a multi-threaded runtime. [`boxed_maybe_send`](crate::MaybeSendFutureExt::boxed_maybe_send)
+/// boxes the future the right way for the target
+pub trait IconSource: MaybeSend + MaybeSync {
+  /// Unique name, used to find the source inside of [`IconSources`]
+  fn name(&self) -> &str;
+
+  /// Every rank of icon this source could produce. Used to stop loading
+  /// early when `best_matches_only` is set, as soon as none of the pending
+  /// sources could produce a better icon than the current best match.
+  /// Defaults to all of them, so that custom sources are always waited for
+  fn ranks(&self) -> &[RepoIconRank] {
+    &RepoIconRank::ALL
+  }
+
+  fn load<'a>(
+    &'a self,
+    ctx: &'a IconSourceContext<'a>,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>>;
+}
+
+/// An ordered list of the sources that get loaded for a repo.
+/// Defaults to the built-in avatar, space emoji, repo files, homepage & readme sources
+pub struct IconSources(Vec<Box<dyn IconSource>>);
+
+impl IconSources {
+  /// Create an empty list of sources
+  pub fn new() -> Self {
+    Self(Vec::new())
+  }
+
+  pub fn push<S: IconSource + 'static>(&mut self, source: S) -> &mut Self {
+    self.0.push(Box::new(source));
+    self
+  }
+
+  pub fn insert<S: IconSource + 'static>(&mut self, index: usize, source: S) -> &mut Self {
+    self.0.insert(index, Box::new(source));
+    self
+  }
+
+  pub fn remove(&mut self, name: &str) -> Option<Box<dyn IconSource>> {
+    let index = self.position(name)?;
+    Some(self.0.remove(index))
+  }
+
+  /// Move a source to a new position in the list
+  pub fn reorder(&mut self, name: &str, index: usize) -> &mut Self {
+    if let Some(source) = self.remove(name) {
+      self.0.insert(index.min(self.0.len()), source);
+    }
+    self
+  }
+
+  pub fn position(&self, name: &str) -> Option<usize> {
+    self.0.iter().position(|source| source.name() == name)
+  }
+
+  pub fn get(&self, name: &str) -> Option<&dyn IconSource> {
+    self
+      .0
+      .iter()
+      .find(|source| source.name() == name)
+      .map(|source| source.as_ref())
+  }
+
+  pub fn names(&self) -> Vec<&str> {
+    self.0.iter().map(|source| source.name()).collect()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &dyn IconSource> {
+    self.0.iter().map(|source| source.as_ref())
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+}
+
+impl Default for IconSources {
+  fn default() -> Self {
+    let mut sources = Self::new();
+    sources
+      .push(AvatarSource)
+      .push(SpaceEmojiSource)
+      .push(RepoFilesSource)
+      .push(HomepageSource)
+      .push(ReadmeSource);
+    sources
+  }
+}
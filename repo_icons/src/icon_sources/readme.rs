@@ -0,0 +1,87 @@
+use super::{IconSource, IconSourceContext};
+use crate::{
+  github_api::Readme, BoxFuture, Explanation, MaybeSendFutureExt, RejectedCandidate, RepoIcon,
+  RepoIconKind, RepoIconRank, RepoIconsError,
+};
+
+/// Try and extract images from the readme website, or directly in it
+pub struct ReadmeSource;
+
+impl ReadmeSource {
+  pub const NAME: &'static str = "readme";
+}
+
+impl IconSource for ReadmeSource {
+  fn name(&self) -> &str {
+    Self::NAME
+  }
+
+  fn ranks(&self) -> &[RepoIconRank] {
+    &[RepoIconRank::ReadmeImage]
+  }
+
+  fn load<'a>(
+    &'a self,
+    ctx: &'a IconSourceContext<'a>,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+    async move {
+      let images = match Readme::load_with_ref(
+        ctx.client,
+        ctx.owner,
+        ctx.repo,
+        ctx.git_ref,
+        ctx.subpath,
+      )
+      .await
+      {
+        Ok(Some(images)) => images,
+        Ok(None) | Err(RepoIconsError::NotFound { .. }) => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+      };
+
+      let mut image = None;
+
+      for candidate in images {
+        if image.is_none() && candidate.in_primary_heading {
+          image = Some(candidate);
+          continue;
+        }
+
+        if ctx.explain {
+          ctx.reject(RejectedCandidate {
+            candidate: candidate.src.to_string(),
+            score: candidate.score(),
+            reason: if candidate.in_primary_heading {
+              "outranked by another image".to_string()
+            } else {
+              "not in primary heading".to_string()
+            },
+          });
+        }
+      }
+
+      let image = match image {
+        Some(image) => image,
+        None => return Ok(Vec::new()),
+      };
+
+      let score = image.score();
+      let mut icon = RepoIcon::load_with_client(
+        ctx.client,
+        image.src,
+        image.headers,
+        RepoIconKind::ReadmeImage,
+      )
+      .await?;
+
+      if ctx.explain {
+        let mut explanation = Explanation::new(icon.kind.rank());
+        explanation.score = Some(score);
+        icon.explanation = Some(explanation);
+      }
+
+      Ok(vec![icon])
+    }
+    .boxed_maybe_send()
+  }
+}
@@ -0,0 +1,90 @@
+use super::{IconSource, IconSourceContext};
+use crate::{
+  github_api::{self, Repo},
+  BoxFuture, Explanation, MaybeSendFutureExt, RepoIcon, RepoIconRank, RepoIconsError,
+};
+use futures::future::try_join_all;
+
+/// Icons committed to the repo, or referenced by the `icon` field of a `package.json`
+pub struct RepoFilesSource;
+
+impl RepoFilesSource {
+  pub const NAME: &'static str = "repo_files";
+}
+
+impl IconSource for RepoFilesSource {
+  fn name(&self) -> &str {
+    Self::NAME
+  }
+
+  fn ranks(&self) -> &[RepoIconRank] {
+    &[
+      RepoIconRank::IconField,
+      RepoIconRank::RepoFile,
+      RepoIconRank::Framework,
+    ]
+  }
+
+  fn load<'a>(
+    &'a self,
+    ctx: &'a IconSourceContext<'a>,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+    async move {
+      let icon_files = github_api::get_repo_icon_files(
+        ctx.client,
+        ctx.owner,
+        ctx.repo,
+        ctx.git_ref,
+        ctx.subpath,
+        ctx.explain,
+      )
+      .await;
+      let icon_files = match icon_files {
+        Ok(icon_files) => icon_files,
+        // empty & missing repos simply don't have any files,
+        // but other failures are worth reporting
+        Err(
+          err @ (RepoIconsError::RateLimited { .. }
+          | RepoIconsError::Unauthorized { .. }
+          | RepoIconsError::Network { .. }),
+        ) => return Err(err),
+        Err(_) => return Ok(Vec::new()),
+      };
+
+      for rejected in icon_files.rejected {
+        ctx.reject(rejected);
+      }
+
+      let is_icon_field = icon_files.is_icon_field;
+      let mut icons = try_join_all(
+        icon_files
+          .files
+          .into_iter()
+          .map(|(blob, score)| async move {
+            let mut icon =
+              RepoIcon::load_repo_file_with_client(ctx.client, blob, is_icon_field).await?;
+
+            if ctx.explain {
+              let mut explanation = Explanation::new(icon.kind.rank());
+              explanation.score = Some(score);
+              icon.explanation = Some(explanation);
+            }
+
+            Ok::<_, RepoIconsError>(icon)
+          }),
+      )
+      .await?;
+
+      if let Ok(Repo { private, .. }) =
+        Repo::load_with_client(ctx.client, ctx.owner, ctx.repo).await
+      {
+        for icon in &mut icons {
+          icon.set_repo_private_with_client(ctx.client, private);
+        }
+      }
+
+      Ok(icons)
+    }
+    .boxed_maybe_send()
+  }
+}
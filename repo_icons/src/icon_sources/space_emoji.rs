@@ -0,0 +1,45 @@
+use super::{IconSource, IconSourceContext};
+use crate::{
+  forges::hugging_face, BoxFuture, Forge, MaybeSendFutureExt, RepoIcon, RepoIconKind, RepoIconRank,
+  RepoIconsError,
+};
+use std::collections::HashMap;
+
+/// The emoji & gradient shown on the card of a Hugging Face Space
+pub struct SpaceEmojiSource;
+
+impl SpaceEmojiSource {
+  pub const NAME: &'static str = "space_emoji";
+}
+
+impl IconSource for SpaceEmojiSource {
+  fn name(&self) -> &str {
+    Self::NAME
+  }
+
+  fn ranks(&self) -> &[RepoIconRank] {
+    &[RepoIconRank::SpaceEmoji]
+  }
+
+  fn load<'a>(
+    &'a self,
+    ctx: &'a IconSourceContext<'a>,
+  ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+    async move {
+      if ctx.client.host().forge() != Forge::HuggingFace {
+        return Ok(Vec::new());
+      }
+
+      let url = match hugging_face::get_space_icon(ctx.client, ctx.owner, ctx.repo).await? {
+        Some(url) => url,
+        None => return Ok(Vec::new()),
+      };
+
+      Ok(vec![
+        RepoIcon::load_with_client(ctx.client, url, HashMap::new(), RepoIconKind::SpaceEmoji)
+          .await?,
+      ])
+    }
+    .boxed_maybe_send()
+  }
+}
@@ -17,8 +17,6 @@
 #[macro_use]
 extern crate log;
 #[macro_use]
-extern crate gh_api;
-#[macro_use]
 extern crate derivative;
 #[macro_use]
 extern crate serde_with;
@@ -26,12 +24,33 @@ extern crate serde_with;
 #[macro_use]
 mod macros;
 mod blacklist;
+mod cache;
+mod client;
+mod error;
+mod explain;
+mod forges;
 mod github_api;
+mod icon_sources;
+mod maybe_send;
 mod repo_icon;
 mod repo_icons;
+mod repo_icons_options;
+mod repo_slug;
 
+pub use cache::*;
+pub use client::*;
+pub use error::*;
+pub use explain::*;
+pub use forges::{Forge, ForgeHost};
 pub use gh_api::*;
 pub use github_api::readme::*;
+pub use github_api::{rate_limit, GithubBackend, HttpBackend, RateLimit, RetryPolicy};
+#[cfg(not(target_arch = "wasm32"))]
+pub use github_api::{FixtureBackend, RecordBackend};
+pub use icon_sources::*;
+pub use maybe_send::*;
 pub use repo_icon::*;
 pub use repo_icons::*;
+pub use repo_icons_options::*;
+pub use repo_slug::*;
 pub use site_icons::*;
@@ -0,0 +1,57 @@
+use std::future::Future;
+
+/// A boxed future that is `Send` on native targets, so that loads can be
+/// spawned onto a multi-threaded runtime. On wasm it's local to the thread
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxFuture<'a, T> = futures::future::BoxFuture<'a, T>;
+#[cfg(target_arch = "wasm32")]
+pub type BoxFuture<'a, T> = futures::future::LocalBoxFuture<'a, T>;
+
+/// A boxed stream that is `Send` on native targets, see [`BoxFuture`]
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxStream<'a, T> = futures::stream::BoxStream<'a, T>;
+#[cfg(target_arch = "wasm32")]
+pub type BoxStream<'a, T> = futures::stream::LocalBoxStream<'a, T>;
+
+/// `Send` on native targets, implemented for everything on wasm
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// `Sync` on native targets, implemented for everything on wasm
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSync: Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Sync + ?Sized> MaybeSync for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSync {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSync for T {}
+
+pub trait MaybeSendFutureExt: Future + Sized {
+  /// Box the future into a [`BoxFuture`], like `boxed` on native
+  /// targets & `boxed_local` on wasm
+  fn boxed_maybe_send<'a>(self) -> BoxFuture<'a, Self::Output>
+  where
+    Self: MaybeSend + 'a,
+  {
+    Box::pin(self)
+  }
+}
+
+impl<F: Future> MaybeSendFutureExt for F {}
+
+/// Fails to compile if loading stops being `Send` on native targets
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(dead_code)]
+fn assert_load_is_send(client: &crate::RepoIconsClient, options: &crate::RepoIconsOptions) {
+  fn is_send<T: Send>(_: T) {}
+
+  is_send(crate::RepoIcons::load_with_client(client, "", "", options));
+  is_send(crate::RepoIcons::load_many(&[""], options));
+}
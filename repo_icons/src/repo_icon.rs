@@ -1,24 +1,29 @@
-use crate::github_api::{get_redirected_user, stripped_owner_lowercase};
+use crate::{
+  github_api::{get_redirected_user, send, stripped_owner_lowercase, Repo},
+  Explanation, Forge, RepoIconsClient, RepoIconsError,
+};
 use data_url::DataUrl;
 use futures::{
   join,
   stream::{self, LocalBoxStream},
   StreamExt,
 };
-use gh_api::get_token;
 #[cfg(feature = "image")]
-use image::{io::Reader as ImageReader, DynamicImage, ImageFormat};
+use image::{DynamicImage, ImageFormat};
 #[cfg(target_arch = "wasm32")]
 use js_sys::Uint8Array;
 use maplit::hashmap;
-use reqwest::{IntoUrl, Response};
+#[cfg(feature = "image")]
+use once_cell::sync::OnceCell;
+use reqwest::{header::HeaderMap, IntoUrl, Response};
 use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
 use site_icons::{IconInfo, IconKind};
+#[cfg(feature = "image")]
+use std::sync::Arc;
 use std::{
   cmp::Ordering,
   collections::HashMap,
   convert::TryInto,
-  error::Error,
   fmt::{self, Display},
   iter,
 };
@@ -39,7 +44,8 @@ pub enum Framework {
 
 impl From<&RepoFile> for Option<Framework> {
   fn from(file: &RepoFile) -> Self {
-    Some(match &file.sha[..] {
+    // forges without blob shas can't be told apart from their defaults
+    Some(match file.sha.as_deref()? {
       "df36fcfb72584e00488330b560ebcf34a41c64c2"
       | "c7b9a43c8cd16d0b434adaf513fcacb340809a11"
       | "f3d2503fc2a44b5053b0837ebea6e87a2d339a43" => Framework::Vue,
@@ -62,7 +68,8 @@ pub struct RepoFile {
   pub github: String,
   pub commit_sha: String,
 
-  pub sha: String,
+  /// `None` on forges whose listings don't include blob shas
+  pub sha: Option<String>,
   pub path: String,
 }
 
@@ -80,21 +87,58 @@ impl Ord for RepoFile {
 
 impl PartialEq for RepoFile {
   fn eq(&self, other: &Self) -> bool {
-    self.github == other.github && self.sha == other.sha
+    self.github == other.github
+      && match (&self.sha, &other.sha) {
+        (Some(sha), Some(other_sha)) => sha == other_sha,
+        _ => self.path == other.path,
+      }
   }
 }
 
-// NOTE: When you change the order of these, make sure
-// to update the order of the hardcoded if statements
-// for the best_matches option, otherwise you'll get
-// inconsistent results when that option is set to
-// true or false
-#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq)]
+/// The priority of each [`RepoIconKind`], from best to worst.
+/// This is the single source of truth for how icons are ranked,
+/// and is also used to decide when `best_matches_only` can stop early
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepoIconRank {
+  IconField,
+  Avatar,
+  SpaceEmoji,
+  AppIcon,
+  SiteFavicon,
+  RepoFile,
+  ReadmeImage,
+  OrgAvatar,
+  SiteLogo,
+  Framework,
+  UserAvatarFallback,
+}
+
+impl RepoIconRank {
+  pub const ALL: [RepoIconRank; 11] = [
+    RepoIconRank::IconField,
+    RepoIconRank::Avatar,
+    RepoIconRank::SpaceEmoji,
+    RepoIconRank::AppIcon,
+    RepoIconRank::SiteFavicon,
+    RepoIconRank::RepoFile,
+    RepoIconRank::ReadmeImage,
+    RepoIconRank::OrgAvatar,
+    RepoIconRank::SiteLogo,
+    RepoIconRank::Framework,
+    RepoIconRank::UserAvatarFallback,
+  ];
+}
+
+/// Ordered by [`RepoIconRank`], the order of the variants is irrelevant
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RepoIconKind {
   IconField {
     file: RepoFile,
   },
   Avatar,
+  /// Synthesized from the emoji & gradient of a Hugging Face Space
+  SpaceEmoji,
   AppIcon {
     homepage: Url,
   },
@@ -116,6 +160,47 @@ pub enum RepoIconKind {
   UserAvatarFallback,
 }
 
+impl RepoIconKind {
+  pub fn rank(&self) -> RepoIconRank {
+    match self {
+      RepoIconKind::IconField { .. } => RepoIconRank::IconField,
+      RepoIconKind::Avatar => RepoIconRank::Avatar,
+      RepoIconKind::SpaceEmoji => RepoIconRank::SpaceEmoji,
+      RepoIconKind::AppIcon { .. } => RepoIconRank::AppIcon,
+      RepoIconKind::SiteFavicon { .. } => RepoIconRank::SiteFavicon,
+      RepoIconKind::RepoFile { .. } => RepoIconRank::RepoFile,
+      RepoIconKind::ReadmeImage => RepoIconRank::ReadmeImage,
+      RepoIconKind::OrgAvatar => RepoIconRank::OrgAvatar,
+      RepoIconKind::SiteLogo { .. } => RepoIconRank::SiteLogo,
+      RepoIconKind::Framework { .. } => RepoIconRank::Framework,
+      RepoIconKind::UserAvatarFallback => RepoIconRank::UserAvatarFallback,
+    }
+  }
+}
+
+impl Ord for RepoIconKind {
+  fn cmp(&self, other: &Self) -> Ordering {
+    use RepoIconKind::*;
+
+    self
+      .rank()
+      .cmp(&other.rank())
+      .then_with(|| match (self, other) {
+        (AppIcon { homepage: a }, AppIcon { homepage: b })
+        | (SiteFavicon { homepage: a }, SiteFavicon { homepage: b })
+        | (SiteLogo { homepage: a }, SiteLogo { homepage: b }) => a.cmp(b),
+        (Framework { framework: a, .. }, Framework { framework: b, .. }) => a.cmp(b),
+        _ => Ordering::Equal,
+      })
+  }
+}
+
+impl PartialOrd for RepoIconKind {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
 impl From<(Url, IconKind)> for RepoIconKind {
   fn from((homepage, kind): (Url, IconKind)) -> Self {
     match kind {
@@ -131,6 +216,7 @@ impl Display for RepoIconKind {
     match self {
       RepoIconKind::IconField { .. } => write!(f, "icon_field"),
       RepoIconKind::Avatar { .. } => write!(f, "avatar"),
+      RepoIconKind::SpaceEmoji => write!(f, "space_emoji"),
       RepoIconKind::UserAvatarFallback => write!(f, "user_avatar_fallback"),
       RepoIconKind::OrgAvatar => write!(f, "org_avatar"),
       RepoIconKind::AppIcon { .. } => write!(f, "app_icon"),
@@ -167,6 +253,7 @@ impl Serialize for RepoIconKind {
       }
       RepoIconKind::ReadmeImage
       | RepoIconKind::Avatar
+      | RepoIconKind::SpaceEmoji
       | RepoIconKind::OrgAvatar
       | RepoIconKind::UserAvatarFallback => {}
     }
@@ -197,6 +284,7 @@ impl<'de> Deserialize<'de> for RepoIconKind {
       "user_avatar_fallback" => RepoIconKind::UserAvatarFallback,
       "org_avatar" => RepoIconKind::OrgAvatar,
       "avatar" => RepoIconKind::Avatar,
+      "space_emoji" => RepoIconKind::SpaceEmoji,
       "app_icon" => RepoIconKind::AppIcon {
         homepage: fields.homepage.unwrap(),
       },
@@ -231,11 +319,23 @@ pub struct RepoIcon {
   #[serde(flatten)]
   pub info: IconInfo,
 
+  /// Only set when loaded with [`RepoIconsOptions::explain`](crate::RepoIconsOptions::explain)
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  #[derivative(PartialEq = "ignore")]
+  pub explanation: Option<Explanation>,
+
+  /// The client the icon was loaded with, which it's downloaded
+  /// with too. Falls back to the global client
+  #[serde(skip)]
+  #[derivative(PartialEq = "ignore")]
+  #[derivative(Debug = "ignore")]
+  client: Option<RepoIconsClient>,
+
   #[cfg(feature = "image")]
   #[serde(skip)]
   #[derivative(PartialEq = "ignore")]
   #[derivative(Debug = "ignore")]
-  image: RefCell<Option<Rc<DynamicImage>>>,
+  image: OnceCell<Arc<DynamicImage>>,
 }
 
 impl RepoIcon {
@@ -254,12 +354,14 @@ impl RepoIcon {
       headers,
       kind,
       info,
+      explanation: None,
+      client: None,
       #[cfg(feature = "image")]
-      image: RefCell::new(None),
+      image: OnceCell::new(),
     }
   }
 
-  pub async fn load<U: IntoUrl>(url: U, kind: RepoIconKind) -> Result<Self, Box<dyn Error>> {
+  pub async fn load<U: IntoUrl>(url: U, kind: RepoIconKind) -> Result<Self, RepoIconsError> {
     Self::load_with_headers(url.into_url()?, HashMap::new(), kind).await
   }
 
@@ -267,39 +369,67 @@ impl RepoIcon {
     url: U,
     headers: HashMap<String, String>,
     kind: RepoIconKind,
-  ) -> Result<Self, Box<dyn Error>> {
+  ) -> Result<Self, RepoIconsError> {
+    Self::load_with_client(RepoIconsClient::global(), url, headers, kind).await
+  }
+
+  /// Load the info of the icon through the [`GithubBackend`](crate::GithubBackend) of the client
+  pub async fn load_with_client<U: IntoUrl>(
+    client: &RepoIconsClient,
+    url: U,
+    headers: HashMap<String, String>,
+    kind: RepoIconKind,
+  ) -> Result<Self, RepoIconsError> {
     let url = url.into_url()?;
-    let info = IconInfo::load(url.clone(), (&headers).try_into()?, None).await?;
-    Ok(Self::new_with_headers(url, headers, kind, info))
+    let info = client
+      .backend()
+      .icon_info(
+        client.http(),
+        &url,
+        (&headers).try_into().map_err(RepoIconsError::other)?,
+      )
+      .await?;
+    Ok(Self::new_with_headers(url, headers, kind, info).with_client(client))
+  }
+
+  /// Download the icon with the client, instead of the global one
+  pub fn with_client(mut self, client: &RepoIconsClient) -> Self {
+    self.client = Some(client.clone());
+    self
+  }
+
+  /// The client the icon is downloaded with
+  pub fn client(&self) -> &RepoIconsClient {
+    self
+      .client
+      .as_ref()
+      .unwrap_or_else(|| RepoIconsClient::global())
   }
 
-  pub async fn load_user_avatar(owner: &str, repo: &str) -> Result<Self, Box<dyn Error>> {
+  pub async fn load_user_avatar(owner: &str, repo: &str) -> Result<Self, RepoIconsError> {
+    Self::load_user_avatar_with_client(RepoIconsClient::global(), owner, repo).await
+  }
+
+  pub async fn load_user_avatar_with_client(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+  ) -> Result<Self, RepoIconsError> {
     let owner = owner.to_lowercase();
     let repo = repo.to_lowercase();
 
-    let docs = regex!("^(docs|documentation)$");
-    let fallback =
-      !repo.contains(&stripped_owner_lowercase(&owner)) && !docs.is_match(&repo).unwrap();
-
     let (redirected_user, owner_avatar) = join!(
-      get_redirected_user(&owner, &repo),
-      RepoIcon::load(
-        format!("https://github.com/{}.png", owner),
-        RepoIconKind::Avatar,
+      get_redirected_user(client, &owner, &repo),
+      RepoIcon::load_with_client(
+        client,
+        client.host().avatar_url(&owner),
+        HashMap::new(),
+        RepoIconKind::Avatar
       )
     );
 
     let (user, is_org) = redirected_user?;
-
-    let kind = if fallback {
-      if is_org {
-        RepoIconKind::OrgAvatar
-      } else {
-        RepoIconKind::UserAvatarFallback
-      }
-    } else {
-      RepoIconKind::Avatar
-    };
+    let kind = owner_avatar_kind(&owner, &repo, is_org);
 
     if user == owner {
       let mut avatar = owner_avatar?;
@@ -307,25 +437,87 @@ impl RepoIcon {
       return Ok(avatar);
     }
 
-    RepoIcon::load(format!("https://github.com/{}.png", user), kind).await
+    RepoIcon::load_with_client(
+      client,
+      client.host().avatar_url(&user),
+      HashMap::new(),
+      kind,
+    )
+    .await
   }
 
-  pub async fn load_repo_file(file: RepoFile, is_icon_field: bool) -> Result<Self, Box<dyn Error>> {
-    let url = Url::parse(&format!(
-      "https://api.github.com/repos/{}/git/blobs/{}",
-      file.github, file.sha
+  /// The avatar of the repo, falling back to the avatar of its owner.
+  /// For forges with repo avatars, which GitHub doesn't have
+  pub async fn load_repo_avatar(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+  ) -> Result<Option<Self>, RepoIconsError> {
+    let Repo {
+      owner: user,
+      private,
+      avatar,
+      ..
+    } = Repo::load_with_client(client, owner, repo).await?;
+
+    let (url, kind) = match (avatar, user.avatar_url) {
+      (Some(avatar), _) => (avatar, RepoIconKind::Avatar),
+      (None, Some(avatar)) => (
+        avatar,
+        owner_avatar_kind(
+          &user.login.to_lowercase(),
+          &repo.to_lowercase(),
+          user.r#type == "Organization",
+        ),
+      ),
+      (None, None) => return Ok(None),
+    };
+
+    let mut headers = HashMap::new();
+    if private {
+      if let Some(token) = client.token() {
+        headers.insert(
+          "Authorization".to_string(),
+          client.host().auth_header(&token),
+        );
+      }
+    }
+
+    Ok(Some(
+      RepoIcon::load_with_client(client, url, headers, kind).await?,
     ))
-    .unwrap();
+  }
 
-    let mut headers = hashmap! {
-      "Accept".to_string() => "application/vnd.github.raw".to_string(),
+  pub async fn load_repo_file(file: RepoFile, is_icon_field: bool) -> Result<Self, RepoIconsError> {
+    Self::load_repo_file_with_client(RepoIconsClient::global(), file, is_icon_field).await
+  }
+
+  pub async fn load_repo_file_with_client(
+    client: &RepoIconsClient,
+    file: RepoFile,
+    is_icon_field: bool,
+  ) -> Result<Self, RepoIconsError> {
+    let host = client.host();
+
+    let (url, mut headers) = match (host.forge(), &file.sha) {
+      (Forge::Github, Some(sha)) => (
+        host.api_url(&format!("repos/{}/git/blobs/{}", file.github, sha)),
+        hashmap! {
+          "Accept".to_string() => "application/vnd.github.raw".to_string(),
+        },
+      ),
+      _ => (
+        host.file_url(&file.github, &file.commit_sha, &file.path),
+        HashMap::new(),
+      ),
     };
 
-    if let Some(token) = get_token() {
-      headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+    if let Some(token) = client.token() {
+      headers.insert("Authorization".to_string(), host.auth_header(&token));
     }
 
-    RepoIcon::load_with_headers(
+    RepoIcon::load_with_client(
+      client,
       url,
       headers,
       if is_icon_field {
@@ -341,46 +533,54 @@ impl RepoIcon {
     .await
   }
 
+  /// Point files of public repos to their raw url, on the host of the client the icon was loaded with
   pub fn set_repo_private(&mut self, is_private: bool) {
+    let client = self.client().clone();
+    self.set_repo_private_with_client(&client, is_private);
+  }
+
+  pub fn set_repo_private_with_client(&mut self, client: &RepoIconsClient, is_private: bool) {
     use RepoIconKind::*;
 
     if let Framework { file, .. } | RepoFile { file } | IconField { file } = &mut self.kind {
       if !is_private {
         self.headers.clear();
-        self.url = Url::parse(&format!(
-          "https://raw.githubusercontent.com/{}/{}/{}",
-          file.github, file.commit_sha, file.path
-        ))
-        .unwrap();
+        self.url = client
+          .host()
+          .raw_url(&file.github, &file.commit_sha, &file.path);
       }
     }
   }
 
-  async fn response(&self) -> Result<IconResponse, Box<dyn Error>> {
+  async fn response(&self) -> Result<IconResponse, RepoIconsError> {
     if self.url.scheme() == "data" {
+      let invalid_image = |message: &str| RepoIconsError::InvalidImage {
+        url: None,
+        message: message.to_string(),
+      };
+
       let url = self.url.to_string();
-      let data = DataUrl::process(&url).map_err(|_| "failed to parse data uri")?;
+      let data = DataUrl::process(&url).map_err(|_| invalid_image("failed to parse data uri"))?;
       let (body, _fragment) = data
         .decode_to_vec()
-        .map_err(|_| "invalid base64 in data uri")?;
+        .map_err(|_| invalid_image("invalid base64 in data uri"))?;
 
       return Ok(IconResponse::DataURI(body));
     }
 
-    let res = reqwest::Client::new()
-      .get(self.url.clone())
-      .headers((&self.headers).try_into()?)
-      .send()
-      .await
-      .map_err(|e| format!("{}: {:?}", self.url, e))?
-      .error_for_status()
-      .map_err(|e| format!("{}: {:?}", self.url, e))?;
+    let headers: HeaderMap = (&self.headers).try_into().map_err(RepoIconsError::other)?;
+
+    let client = self.client();
+    let res = send(client, &self.url, || {
+      client.http().get(self.url.clone()).headers(headers.clone())
+    })
+    .await?;
 
     Ok(IconResponse::Network(res))
   }
 
   #[cfg(target_arch = "wasm32")]
-  pub async fn js_stream(&self) -> Result<ReadableStream, Box<dyn Error>> {
+  pub async fn js_stream(&self) -> Result<ReadableStream, RepoIconsError> {
     Ok(match self.response().await? {
       IconResponse::DataURI(body) => {
         let body = Uint8Array::from(&body[..]);
@@ -396,7 +596,7 @@ impl RepoIcon {
 
   pub async fn stream<'a>(
     &self,
-  ) -> Result<LocalBoxStream<'a, Result<Vec<u8>, reqwest::Error>>, Box<dyn Error>> {
+  ) -> Result<LocalBoxStream<'a, Result<Vec<u8>, reqwest::Error>>, RepoIconsError> {
     Ok(match self.response().await? {
       IconResponse::DataURI(body) => stream::iter(iter::once(Ok(body))).boxed_local(),
       IconResponse::Network(res) => {
@@ -409,29 +609,112 @@ impl RepoIcon {
     })
   }
 
+  /// Download the whole icon
+  pub async fn bytes(&self) -> Result<Vec<u8>, RepoIconsError> {
+    Ok(match self.response().await? {
+      IconResponse::DataURI(body) => body,
+      IconResponse::Network(res) => res.bytes().await?.to_vec(),
+    })
+  }
+
+  /// Download & decode the icon, which is kept for the next call.
+  /// SVGs aren't supported, see [`decode_image`] for the formats that are
   #[cfg(feature = "image")]
-  pub async fn image(&self) -> Result<Rc<DynamicImage>, Box<dyn Error>> {
-    if let Some(image) = self.image.borrow().clone() {
-      return Ok(image);
+  pub async fn image(&self) -> Result<Arc<DynamicImage>, RepoIconsError> {
+    if let Some(image) = self.image.get() {
+      return Ok(image.clone());
     }
 
-    let data = self.data().await?;
-    let mut reader = ImageReader::new(Cursor::new(data));
+    if let IconInfo::SVG { .. } = self.info {
+      return Err(RepoIconsError::InvalidImage {
+        url: Some(self.url.clone()),
+        message: "SVGs can't be decoded".to_string(),
+      });
+    }
 
-    reader.set_format(match self.info {
-      IconInfo::PNG { .. } => ImageFormat::Png,
-      IconInfo::JPEG { .. } => ImageFormat::Jpeg,
-      IconInfo::ICO { .. } => ImageFormat::Ico,
-      IconInfo::SVG { .. } => return Err("not supported!".into()),
-    });
+    let image = decode_image(&self.bytes().await?).map_err(|err| match err {
+      RepoIconsError::InvalidImage { message, .. } => RepoIconsError::InvalidImage {
+        url: Some(self.url.clone()),
+        message,
+      },
+      err => err,
+    })?;
 
-    let image = Rc::new(reader.decode()?);
-    *self.image.borrow_mut() = Some(image.clone());
-    Ok(image)
+    Ok(self.image.get_or_init(|| Arc::new(image)).clone())
   }
 }
 
+/// Decode a PNG, JPEG, ICO, GIF or WebP icon. The format is sniffed from the data, as
+/// icons are often served with the wrong content type. ICOs decode to their largest image
+#[cfg(feature = "image")]
+pub fn decode_image(data: &[u8]) -> Result<DynamicImage, RepoIconsError> {
+  let invalid_image = |message: String| RepoIconsError::InvalidImage { url: None, message };
+
+  let format = image::guess_format(data).map_err(|err| invalid_image(err.to_string()))?;
+  let largest_image;
+  let data = match format {
+    ImageFormat::Ico => {
+      largest_image = largest_ico_image(data)
+        .ok_or_else(|| invalid_image("invalid ico directory".to_string()))?;
+      &largest_image[..]
+    }
+    ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Gif | ImageFormat::WebP => data,
+    format => return Err(invalid_image(format!("unsupported format {:?}", format))),
+  };
+
+  image::load_from_memory_with_format(data, format).map_err(|err| invalid_image(err.to_string()))
+}
+
+/// Rewrite an ICO to only contain its largest image, as the decoder
+/// picks the one with the deepest colors over the biggest one
+#[cfg(feature = "image")]
+fn largest_ico_image(data: &[u8]) -> Option<Vec<u8>> {
+  let count = u16::from_le_bytes(data.get(4..6)?.try_into().ok()?);
+
+  let (entry, image) = (0..count as usize)
+    .filter_map(|i| {
+      let entry = data.get(6 + 16 * i..22 + 16 * i)?;
+      let size = u32::from_le_bytes(entry[8..12].try_into().ok()?) as usize;
+      let offset = u32::from_le_bytes(entry[12..16].try_into().ok()?) as usize;
+      let image = data.get(offset..offset.checked_add(size)?)?;
+
+      // a width or height of 0 means 256
+      let dimension = |byte: u8| if byte == 0 { 256 } else { byte as u32 };
+      let area = dimension(entry[0]) * dimension(entry[1]);
+
+      Some((area, entry, image))
+    })
+    .max_by_key(|(area, ..)| *area)
+    .map(|(_, entry, image)| (entry, image))?;
+
+  let mut ico = Vec::with_capacity(22 + image.len());
+  ico.extend_from_slice(&data[..4]);
+  ico.extend_from_slice(&1u16.to_le_bytes());
+  ico.extend_from_slice(&entry[..12]);
+  ico.extend_from_slice(&22u32.to_le_bytes());
+  ico.extend_from_slice(image);
+
+  Some(ico)
+}
+
 enum IconResponse {
   Network(Response),
   DataURI(Vec<u8>),
 }
+
+/// Owner avatars are only treated as the repo's icon when
+/// the repo is named after its owner (or is their docs)
+fn owner_avatar_kind(owner: &str, repo: &str, is_org: bool) -> RepoIconKind {
+  let docs = regex!("^(docs|documentation)$");
+  let fallback = !repo.contains(&stripped_owner_lowercase(owner)) && !docs.is_match(repo).unwrap();
+
+  if fallback {
+    if is_org {
+      RepoIconKind::OrgAvatar
+    } else {
+      RepoIconKind::UserAvatarFallback
+    }
+  } else {
+    RepoIconKind::Avatar
+  }
+}
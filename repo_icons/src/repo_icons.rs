@@ -1,36 +1,35 @@
 use crate::{
-  blacklist::{is_badge_url, is_blacklisted_homepage},
-  get_token,
-  github_api::{self, Readme, Repo},
-  RepoIcon, RepoIconKind,
+  github_api::{preload_repos, send, GRAPHQL_BATCH_SIZE},
+  BoxFuture, BoxStream, Explanation, Forge, IconSource, IconSourceContext, MaybeSendFutureExt,
+  RejectedCandidate, RepoIcon, RepoIconRank, RepoIconsClient, RepoIconsDiagnostic, RepoIconsError,
+  RepoIconsOptions, RepoSlug,
 };
-use async_recursion::async_recursion;
 use futures::{
-  future::{select_all, try_join_all},
-  Future, FutureExt,
+  channel::mpsc,
+  future::{self, select, select_all, Either},
+  stream, FutureExt, StreamExt,
 };
 use futures_timer::Delay;
-use instant::Duration;
+use instant::{Duration, Instant};
 use itertools::Itertools;
 use reqwest::IntoUrl;
 use serde::{ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
-use site_icons::SiteIcons;
 use std::{
   cmp::{max, min},
   collections::HashMap,
   convert::TryInto,
-  error::Error,
+  future::Future,
   ops::{Deref, DerefMut},
-  pin::Pin,
 };
 use vec1::Vec1;
 
-const NO_ICONS_FOUND: &str = "No icons found for repo";
-
 #[derive(Debug)]
 pub struct RepoIconsResult {
-  pub errors: Option<Vec1<String>>,
-  pub icons: Result<RepoIcons, String>,
+  pub errors: Option<Vec1<RepoIconsDiagnostic>>,
+  pub icons: Result<RepoIcons, RepoIconsError>,
+  /// The candidates the sources considered but didn't pick, when explaining.
+  /// Kept even when no icons were found, as they show what was looked at
+  pub rejected: Vec<RejectedCandidate>,
 }
 
 impl Serialize for RepoIconsResult {
@@ -38,9 +37,14 @@ impl Serialize for RepoIconsResult {
   where
     S: Serializer,
   {
-    let mut state = serializer.serialize_struct("RepoIconsResult", 2)?;
+    let mut state = serializer.serialize_struct("RepoIconsResult", 3)?;
     state.serialize_field("errors", &self.errors)?;
     state.serialize_field("icons", &self.icons.as_ref().ok())?;
+    if self.rejected.is_empty() {
+      state.skip_field("rejected")?;
+    } else {
+      state.serialize_field("rejected", &self.rejected)?;
+    }
     state.end()
   }
 }
@@ -52,33 +56,50 @@ impl<'de> Deserialize<'de> for RepoIconsResult {
   {
     #[derive(Deserialize)]
     struct Fields {
-      errors: Option<Vec1<String>>,
+      errors: Option<Vec1<RepoIconsDiagnostic>>,
       icons: Option<RepoIcons>,
+      #[serde(default)]
+      rejected: Vec<RejectedCandidate>,
     }
 
-    let Fields { errors, icons } = Fields::deserialize(deserializer)?;
+    let Fields {
+      errors,
+      icons,
+      rejected,
+    } = Fields::deserialize(deserializer)?;
 
     Ok(RepoIconsResult {
       errors,
-      icons: icons.ok_or_else(|| NO_ICONS_FOUND.to_string()),
+      icons: icons.ok_or(RepoIconsError::NoIconsFound),
+      rejected,
     })
   }
 }
 
+/// What happened to one of the sources, see [`RepoIcons::stream`]
+#[derive(Debug)]
+pub enum RepoIconsEvent {
+  /// A source finished loading, with the icons it found (if any)
+  Loaded {
+    source: String,
+    icons: Vec<RepoIcon>,
+    /// The candidates it didn't pick, when explaining
+    rejected: Vec<RejectedCandidate>,
+  },
+  /// A source failed or timed out
+  Failed(RepoIconsDiagnostic),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RepoIcons(Vec1<RepoIcon>);
 
-#[derive(Debug, Clone)]
-enum LoadedKind {
-  Avatar(RepoIcon),
-  RepoFile(Option<Vec1<RepoIcon>>),
-  ReadmeImage(Option<RepoIcon>),
-  Homepage(Option<Vec1<RepoIcon>>),
-}
-
 impl RepoIcons {
   /// Fetch all the icons. Ordered from highest to lowest resolution
   ///
+  /// With `best_matches_only`, loading stops as soon as none of the pending
+  /// sources could produce a higher ranked icon, so the best match is always
+  /// the same one you'd get by loading everything.
+  ///
   /// ```
   /// # async fn run() {
   /// let icons = RepoIcons::load("facebook", "react", false).await?;
@@ -87,241 +108,355 @@ impl RepoIcons {
   ///   println("{:?}", icon)
   /// }
   /// ```
-  #[async_recursion(?Send)]
   pub async fn load(owner: &str, repo: &str, best_matches_only: bool) -> RepoIconsResult {
-    let mut repo_icons = Vec::new();
+    Self::load_with_options(
+      owner,
+      repo,
+      &RepoIconsOptions::new().best_matches_only(best_matches_only),
+    )
+    .await
+  }
 
-    let mut futures: Vec<Pin<Box<dyn Future<Output = Result<LoadedKind, Box<dyn Error>>>>>> = vec![
-      async {
-        let icon = RepoIcon::load_user_avatar(owner, repo).await?;
-        Ok(LoadedKind::Avatar(icon))
-      }
-      .boxed_local(),
-      async {
-        let blob_icons = match github_api::get_repo_icon_files(owner, repo)
-          .await
-          .ok()
-          .flatten()
-        {
-          Some((is_icon_field, blobs)) => Some(
-            try_join_all(
-              blobs
-                .into_iter()
-                .map(|blob| RepoIcon::load_repo_file(blob, is_icon_field)),
-            )
-            .await?
-            .try_into()
-            .unwrap(),
-          ),
-          None => None,
-        };
+  /// Fetch all the icons, with control over the sources & their timeouts.
+  /// Ordered from highest to lowest resolution
+  ///
+  /// ```
+  /// # async fn run() {
+  /// let options = RepoIconsOptions::new()
+  ///   .disable_source(HomepageSource::NAME)
+  ///   .default_timeout(Duration::from_secs(5));
+  ///
+  /// let icons = RepoIcons::load_with_options("facebook", "react", &options).await?;
+  /// ```
+  pub async fn load_with_options(
+    owner: &str,
+    repo: &str,
+    options: &RepoIconsOptions,
+  ) -> RepoIconsResult {
+    Self::load_with_client(RepoIconsClient::global(), owner, repo, options).await
+  }
 
-        Ok(LoadedKind::RepoFile(blob_icons))
+  /// Fetch all the icons for a [`RepoSlug`], loading it from the forge it
+  /// points to. `hf:owner/name` slugs are loaded from the Hugging Face Hub
+  ///
+  /// ```
+  /// let icons = RepoIcons::load_slug("git@gitlab.com:gitlab-org/gitlab.git", &RepoIconsOptions::new()).await;
+  /// let icons = RepoIcons::load_slug("hf:gradio/hello_world", &RepoIconsOptions::new()).await;
+  /// ```
+  pub async fn load_slug(slug: &str, options: &RepoIconsOptions) -> RepoIconsResult {
+    let host = slug
+      .parse::<RepoSlug>()
+      .ok()
+      .and_then(|slug| slug.forge_host());
+
+    match host {
+      Some(host) if !host.is_github_com() => {
+        RepoIconsClient::for_host(&host)
+          .load_slug(slug, options)
+          .await
       }
-      .boxed_local(),
-      async {
-        let mut icons =
-          SiteIcons::new_with_blacklist(|url| is_blacklisted_homepage(url) || is_badge_url(url));
-
-        let Repo { homepage, .. } = Repo::load(owner, repo).await?;
-
-        let entries = match &homepage {
-          Some(homepage) => {
-            select_all(vec![
-              icons
-                .load_website(homepage.clone(), best_matches_only)
-                .boxed_local(),
-              Delay::new(Duration::from_secs(2))
-                .map(|_| Ok(Vec::new()))
-                .boxed_local(),
-            ])
-            .await
-            .0?
-          }
-          None => Vec::new(),
-        };
+      _ => RepoIconsClient::global().load_slug(slug, options).await,
+    }
+  }
 
-        Ok(LoadedKind::Homepage(
-          entries
-            .into_iter()
-            .map(|icon| {
-              RepoIcon::new(
-                icon.url,
-                (homepage.clone().unwrap(), icon.kind).into(),
-                icon.info,
-              )
-            })
-            .collect::<Vec<_>>()
-            .try_into()
-            .ok(),
-        ))
-      }
-      .boxed_local(),
-      // Try and extract images from the readme website, or directly in it
-      async {
-        let readme = Readme::load(owner, repo).await;
-        let image =
-          readme.and_then(|images| images.into_iter().find(|image| image.in_primary_heading));
-
-        Ok(LoadedKind::ReadmeImage(match image {
-          Some(image) => Some(
-            RepoIcon::load_with_headers(image.src, image.headers, RepoIconKind::ReadmeImage)
-              .await?,
-          ),
-          None => None,
-        }))
+  /// Fetch the icons of many repos, returning a result for each slug in the same order.
+  ///
+  /// The metadata of GitHub repos is looked up in batches with the GraphQL API
+  /// (which needs a token), so only the files & readme are loaded per repo.
+  /// See [`RepoIconsOptions::concurrency`] for how many repos load at once
+  ///
+  /// ```
+  /// let results = RepoIcons::load_many(&["facebook/react", "vuejs/vue"], &RepoIconsOptions::new()).await;
+  /// ```
+  pub async fn load_many<S: AsRef<str>>(
+    slugs: &[S],
+    options: &RepoIconsOptions,
+  ) -> Vec<RepoIconsResult> {
+    Self::load_many_with(RepoIconsClient::global(), slugs, options, |slug| {
+      Self::load_slug(slug, options)
+    })
+    .await
+  }
+
+  /// Preload the GitHub repos of each batch of slugs with the client,
+  /// then load each of them with `load`
+  pub(crate) async fn load_many_with<'a, S, F, Fut>(
+    client: &RepoIconsClient,
+    slugs: &'a [S],
+    options: &RepoIconsOptions,
+    load: F,
+  ) -> Vec<RepoIconsResult>
+  where
+    S: AsRef<str>,
+    F: Fn(&'a str) -> Fut,
+    Fut: Future<Output = RepoIconsResult>,
+  {
+    let mut results = Vec::with_capacity(slugs.len());
+
+    // batches are kept smaller than the caches, so that nothing gets evicted before it's used
+    for batch in slugs.chunks(GRAPHQL_BATCH_SIZE) {
+      if client.host().forge() == Forge::Github && client.token().is_some() {
+        let repos = batch
+          .iter()
+          .filter_map(|slug| slug.as_ref().parse::<RepoSlug>().ok())
+          .filter(|slug| {
+            slug
+              .forge_host()
+              .map(|host| host.web() == client.host().web())
+              .unwrap_or(true)
+          })
+          .map(|slug| (slug.owner, slug.repo))
+          .collect::<Vec<_>>();
+
+        preload_repos(client, &repos).await;
       }
-      .boxed_local(),
-    ];
 
-    let mut previous_loads = Vec::new();
-    let mut found_best_match = false;
+      results.extend(
+        stream::iter(batch)
+          .map(|slug| load(slug.as_ref()))
+          .buffered(options.concurrency)
+          .collect::<Vec<_>>()
+          .await,
+      );
+    }
 
-    let mut errors = Vec::new();
+    results
+  }
 
-    while !futures.is_empty() {
-      let (loaded, index, _) = select_all(&mut futures).await;
-      futures.remove(index);
+  /// Fetch all the icons, making GitHub requests with the given client
+  pub async fn load_with_client(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+    options: &RepoIconsOptions,
+  ) -> RepoIconsResult {
+    Self::load_at(
+      client,
+      owner,
+      repo,
+      options.git_ref.as_deref(),
+      options.subpath.as_deref(),
+      options,
+    )
+    .await
+  }
 
-      let loaded = match loaded {
-        Err(err) => {
-          errors.push(err.to_string());
-          continue;
+  /// Fetch all the icons, overriding the git ref & subpath of the options
+  pub(crate) async fn load_at(
+    client: &RepoIconsClient,
+    owner: &str,
+    repo: &str,
+    git_ref: Option<&str>,
+    subpath: Option<&str>,
+    options: &RepoIconsOptions,
+  ) -> RepoIconsResult {
+    let mut events = Self::stream_at(client, owner, repo, git_ref, subpath, options);
+
+    let mut repo_icons = Vec::new();
+    let mut errors = Vec::new();
+    let mut rejected = Vec::new();
+
+    while let Some(event) = events.next().await {
+      match event {
+        RepoIconsEvent::Loaded {
+          icons,
+          rejected: source_rejected,
+          ..
+        } => {
+          repo_icons.extend(icons);
+          rejected.extend(source_rejected);
         }
-        Ok(loaded) => loaded,
-      };
-
-      match &loaded {
-        LoadedKind::RepoFile(file_icons) => {
-          if let Some(mut file_icons) = file_icons.clone() {
-            for file_icon in &mut file_icons {
-              if let Ok(Repo { private, .. }) = Repo::load(&owner, &repo).await {
-                file_icon.set_repo_private(private);
-              }
+        RepoIconsEvent::Failed(diagnostic) => errors.push(diagnostic),
+      }
+    }
 
-              if matches!(file_icon.kind, RepoIconKind::IconField { .. }) {
-                found_best_match = true;
-              }
-            }
+    repo_icons.sort_by(|a, b| a.info.cmp(&b.info));
+    repo_icons.sort_by(|a, b| a.kind.cmp(&b.kind));
 
-            // this is to ensure it isn't a Framework
-            let has_repo_file = file_icons
-              .iter()
-              .any(|file_icon| matches!(file_icon.kind, RepoIconKind::RepoFile { .. }));
-
-            repo_icons.extend(file_icons);
-
-            if has_repo_file
-              && previous_loads
-                .iter()
-                .any(|loaded| matches!(loaded, LoadedKind::Avatar(_)))
-              && previous_loads
-                .iter()
-                .any(|loaded| matches!(loaded, LoadedKind::Homepage(_)))
-            {
-              // if we have both the avatar & homepage but haven't
-              // found the best match yet, then the repo file is the
-              // best match
-              found_best_match = true;
-            }
-          }
-        }
+    let mut repo_icons = repo_icons
+      .into_iter()
+      .unique_by(|icon| icon.url.clone())
+      .collect::<Vec<_>>();
 
-        LoadedKind::Avatar(user_avatar) => {
-          // found_best_match for RepoFile
-          if let Some(blob_kinds) = previous_loads.iter().find_map(|loaded| {
-            if let LoadedKind::RepoFile(blob_icons) = loaded {
-              Some(blob_icons.as_ref().map(|blob_icons| {
-                blob_icons
-                  .iter()
-                  .map(|blob| blob.kind.clone())
-                  .collect::<Vec<_>>()
-              }))
-            } else {
-              None
-            }
-          }) {
-            if let Some(blob_kinds) = blob_kinds {
-              for blob_kind in blob_kinds {
-                if matches!(blob_kind, RepoIconKind::RepoFile { .. }) {
-                  found_best_match = true;
-                }
+    if options.explain {
+      for (position, icon) in repo_icons.iter_mut().enumerate() {
+        let rank = icon.kind.rank();
+        icon
+          .explanation
+          .get_or_insert_with(|| Explanation::new(rank))
+          .position = position;
+      }
+    }
+
+    let icons: Result<Vec1<RepoIcon>, _> = repo_icons
+      .try_into()
+      .map_err(|_| RepoIconsError::NoIconsFound);
+
+    RepoIconsResult {
+      icons: icons.map(|icons| RepoIcons(icons)),
+      errors: errors.try_into().ok(),
+      rejected,
+    }
+  }
+
+  /// Stream the icons of each source as soon as it finishes loading, so that
+  /// an icon can be shown straight away & replaced once a better one arrives.
+  /// Icons within an event aren't sorted, compare their [`kind`](RepoIcon::kind)
+  /// to find the best one.
+  ///
+  /// The stream ends once every source has finished, the deadline is hit
+  /// or (with `best_matches_only`) the best match has been found
+  ///
+  /// ```
+  /// let mut events = RepoIcons::stream("facebook", "react", &RepoIconsOptions::new());
+  /// let mut best = None;
+  ///
+  /// while let Some(event) = events.next().await {
+  ///   if let RepoIconsEvent::Loaded { icons, .. } = event {
+  ///     for icon in icons {
+  ///       if best.as_ref().map(|best: &RepoIcon| icon.kind < best.kind).unwrap_or(true) {
+  ///         best = Some(icon);
+  ///       }
+  ///     }
+  ///   }
+  /// }
+  /// ```
+  pub fn stream<'a>(
+    owner: &'a str,
+    repo: &'a str,
+    options: &'a RepoIconsOptions,
+  ) -> BoxStream<'a, RepoIconsEvent> {
+    Self::stream_with_client(RepoIconsClient::global(), owner, repo, options)
+  }
+
+  /// Stream the icons of each source, making GitHub requests with the given client.
+  /// See [`stream`](Self::stream)
+  pub fn stream_with_client<'a>(
+    client: &'a RepoIconsClient,
+    owner: &'a str,
+    repo: &'a str,
+    options: &'a RepoIconsOptions,
+  ) -> BoxStream<'a, RepoIconsEvent> {
+    Self::stream_at(
+      client,
+      owner,
+      repo,
+      options.git_ref.as_deref(),
+      options.subpath.as_deref(),
+      options,
+    )
+  }
+
+  pub(crate) fn stream_at<'a>(
+    client: &'a RepoIconsClient,
+    owner: &'a str,
+    repo: &'a str,
+    git_ref: Option<&'a str>,
+    subpath: Option<&'a str>,
+    options: &'a RepoIconsOptions,
+  ) -> BoxStream<'a, RepoIconsEvent> {
+    let (sender, receiver) = mpsc::unbounded();
+
+    // the sources borrow the context, so they're driven from a future that owns it
+    // & their events are sent through a channel
+    let load = async move {
+      let mut pending = options
+        .sources
+        .iter()
+        .filter(|source| options.is_enabled(source.name()))
+        .collect::<Vec<_>>();
+
+      // each source gets its own context, so that its rejected candidates can be told apart
+      let contexts = pending
+        .iter()
+        .map(|_| IconSourceContext {
+          client,
+          owner,
+          repo,
+          subpath,
+          git_ref,
+          best_matches_only: options.best_matches_only,
+          explain: options.explain,
+          rejected: Default::default(),
+        })
+        .collect::<Vec<_>>();
+      let mut pending_contexts = contexts.iter().collect::<Vec<_>>();
+
+      let mut futures = pending
+        .iter()
+        .zip(&contexts)
+        .map(|(&source, ctx)| with_timeout(source.load(ctx), options.timeout_for(source.name())))
+        .collect::<Vec<_>>();
+
+      let start = Instant::now();
+      let mut deadline = options.deadline.map(Delay::new);
+      let mut best_rank = None;
+
+      while !futures.is_empty() {
+        let next = select_all(&mut futures);
+
+        let (loaded, index) = match &mut deadline {
+          Some(deadline) => match select(next, deadline).await {
+            Either::Left(((loaded, index, _), _)) => (loaded, index),
+            Either::Right(_) => {
+              for source in &pending {
+                let _ = sender.unbounded_send(RepoIconsEvent::Failed(RepoIconsDiagnostic::new(
+                  source.name(),
+                  start.elapsed(),
+                  RepoIconsError::Timeout {
+                    after: options.deadline.unwrap(),
+                  },
+                )));
               }
-            } else {
-              found_best_match = true;
+              break;
             }
+          },
+          None => {
+            let (loaded, index, _) = next.await;
+            (loaded, index)
           }
+        };
 
-          repo_icons.push(user_avatar.clone());
-        }
+        futures.remove(index);
+        let source = pending.remove(index);
+        let ctx = pending_contexts.remove(index);
 
-        LoadedKind::ReadmeImage(readme_image) => {
-          if let Some(readme_image) = readme_image {
-            if previous_loads
+        let event = match loaded {
+          Ok(icons) => {
+            best_rank = icons
               .iter()
-              .any(|loaded| matches!(loaded, LoadedKind::Avatar(_)))
-              && previous_loads
-                .iter()
-                .any(|loaded| matches!(loaded, LoadedKind::RepoFile(_)))
-              && previous_loads
-                .iter()
-                .any(|loaded| matches!(loaded, LoadedKind::Homepage(_)))
-            {
-              // if we've already got the Avatar, RepoFile & Homepage,
-              // then the ReadmeImage is the best match
-              found_best_match = true;
+              .map(|icon| icon.kind.rank())
+              .chain(best_rank)
+              .min();
+
+            RepoIconsEvent::Loaded {
+              source: source.name().to_string(),
+              icons,
+              rejected: ctx.take_rejected(),
             }
-
-            repo_icons.push(readme_image.clone());
           }
-        }
+          Err(err) => RepoIconsEvent::Failed(RepoIconsDiagnostic::new(
+            source.name(),
+            start.elapsed(),
+            err,
+          )),
+        };
+        let _ = sender.unbounded_send(event);
 
-        LoadedKind::Homepage(site_icons) => {
-          if let Some(site_icons) = site_icons {
-            repo_icons.extend(site_icons.clone());
-
-            // if it contains AppIcon or SiteFavicon
-            if site_icons.iter().any(|icon| {
-              matches!(
-                icon.kind,
-                RepoIconKind::AppIcon { .. } | RepoIconKind::SiteFavicon { .. }
-              )
-            }) && previous_loads
-              .iter()
-              .any(|loaded| matches!(loaded, LoadedKind::Avatar(_)))
-              && previous_loads
-                .iter()
-                .any(|loaded| matches!(loaded, LoadedKind::RepoFile(_)))
-            {
-              found_best_match = true;
+        if options.best_matches_only {
+          if let Some(rank) = best_rank {
+            if is_best_match(rank, &pending) {
+              break;
             }
           }
         }
       }
+    };
 
-      previous_loads.push(loaded);
-
-      repo_icons.sort_by(|a, b| a.info.cmp(&b.info));
-      repo_icons.sort_by(|a, b| a.kind.cmp(&b.kind));
-
-      if best_matches_only && found_best_match {
-        break;
-      }
-    }
-
-    let repo_icons = repo_icons
-      .into_iter()
-      .unique_by(|icon| icon.url.clone())
-      .collect::<Vec<_>>();
-
-    let icons: Result<Vec1<RepoIcon>, _> = repo_icons
-      .try_into()
-      .map_err(|_| NO_ICONS_FOUND.to_string());
-
-    RepoIconsResult {
-      icons: icons.map(|icons| RepoIcons(icons)),
-      errors: errors.try_into().ok(),
-    }
+    Box::pin(stream::select(
+      receiver,
+      load.into_stream().filter_map(|_| future::ready(None)),
+    ))
   }
 
   /// Fetch all icons using an API endpoint. Ordered from highest to lowest resolution
@@ -338,18 +473,18 @@ impl RepoIcons {
     endpoint: U,
     owner: &str,
     repo: &str,
-  ) -> Result<Self, Box<dyn Error>> {
+  ) -> Result<Self, RepoIconsError> {
     let mut endpoint = endpoint
       .into_url()?
       .join(&format!("{}/{}/all", owner, repo))?;
 
-    if let Some(token) = get_token() {
+    let client = RepoIconsClient::global();
+    if let Some(token) = client.token() {
       endpoint.set_query(Some(&format!("token={}", token)));
     }
 
-    let repo_icons = reqwest::get(endpoint)
+    let repo_icons = send(client, &endpoint, || client.http().get(endpoint.clone()))
       .await?
-      .error_for_status()?
       .json()
       .await?;
 
@@ -457,6 +592,34 @@ impl RepoIcons {
   }
 }
 
+/// Fail the source if it hasn't loaded within the timeout
+fn with_timeout<'a>(
+  load: BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>>,
+  timeout: Option<Duration>,
+) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+  let timeout = match timeout {
+    Some(timeout) => timeout,
+    None => return load,
+  };
+
+  async move {
+    match select(load, Delay::new(timeout)).await {
+      Either::Left((loaded, _)) => loaded,
+      Either::Right(_) => Err(RepoIconsError::Timeout { after: timeout }),
+    }
+  }
+  .boxed_maybe_send()
+}
+
+/// Whether none of the pending sources could produce
+/// an icon that would be ranked higher than `rank`
+fn is_best_match(rank: RepoIconRank, pending: &[&dyn IconSource]) -> bool {
+  pending
+    .iter()
+    .flat_map(|source| source.ranks())
+    .all(|pending_rank| *pending_rank > rank)
+}
+
 impl IntoIterator for RepoIcons {
   type Item = RepoIcon;
   type IntoIter = std::vec::IntoIter<Self::Item>;
@@ -479,3 +642,304 @@ impl DerefMut for RepoIcons {
     &mut self.0
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{Framework, IconInfo, IconSources, RepoFile, RepoIconKind};
+  use futures::channel::oneshot;
+  use std::sync::Mutex;
+  use url::Url;
+
+  /// Finishes once the source before it in the completion order has,
+  /// so that every order can be driven without any timing
+  struct StubSource {
+    name: &'static str,
+    ranks: &'static [RepoIconRank],
+    kinds: Vec<RepoIconKind>,
+    failed: bool,
+    turn: Mutex<Option<oneshot::Receiver<()>>>,
+    next: Mutex<Option<oneshot::Sender<()>>>,
+  }
+
+  impl IconSource for StubSource {
+    fn name(&self) -> &str {
+      self.name
+    }
+
+    fn ranks(&self) -> &[RepoIconRank] {
+      self.ranks
+    }
+
+    fn load<'a>(
+      &'a self,
+      _ctx: &'a IconSourceContext<'a>,
+    ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+      async move {
+        let turn = self.turn.lock().unwrap().take();
+        if let Some(turn) = turn {
+          let _ = turn.await;
+        }
+
+        if let Some(next) = self.next.lock().unwrap().take() {
+          let _ = next.send(());
+        }
+
+        if self.failed {
+          return Err(RepoIconsError::Timeout {
+            after: Duration::from_secs(2),
+          });
+        }
+
+        Ok(
+          self
+            .kinds
+            .iter()
+            .map(|kind| icon(self.name, kind.clone()))
+            .collect(),
+        )
+      }
+      .boxed_maybe_send()
+    }
+  }
+
+  fn icon(source: &str, kind: RepoIconKind) -> RepoIcon {
+    let url = Url::parse(&format!("https://example.com/{}/{}.svg", source, kind)).unwrap();
+    let info = serde_json::from_value::<IconInfo>(serde_json::json!({ "type": "svg" })).unwrap();
+
+    RepoIcon::new(url, kind, info)
+  }
+
+  fn file(path: &str) -> RepoFile {
+    RepoFile {
+      github: "owner/repo".to_string(),
+      commit_sha: "main".to_string(),
+      sha: Some(path.to_string()),
+      path: path.to_string(),
+    }
+  }
+
+  /// Sources that finish in the given order, where `avatar` is the kind of the owner's avatar
+  fn sources(order: &[usize], avatar: &RepoIconKind) -> IconSources {
+    let stubs = [
+      // could produce the best kind of icon, but doesn't
+      (
+        "package_json",
+        &[RepoIconRank::IconField][..],
+        vec![],
+        false,
+      ),
+      (
+        "owner",
+        &[
+          RepoIconRank::Avatar,
+          RepoIconRank::OrgAvatar,
+          RepoIconRank::UserAvatarFallback,
+        ][..],
+        vec![avatar.clone()],
+        false,
+      ),
+      (
+        "site",
+        &[
+          RepoIconRank::AppIcon,
+          RepoIconRank::SiteFavicon,
+          RepoIconRank::SiteLogo,
+        ][..],
+        vec![],
+        true,
+      ),
+      (
+        "files",
+        &[RepoIconRank::RepoFile, RepoIconRank::Framework][..],
+        vec![
+          RepoIconKind::Framework {
+            file: file("public/favicon.ico"),
+            framework: Framework::CreateReactApp,
+          },
+          RepoIconKind::RepoFile {
+            file: file("logo.svg"),
+          },
+        ],
+        false,
+      ),
+      (
+        "readme_images",
+        &[RepoIconRank::ReadmeImage][..],
+        vec![RepoIconKind::ReadmeImage],
+        false,
+      ),
+    ];
+
+    let mut turns = stubs.iter().map(|_| None).collect::<Vec<_>>();
+    let mut nexts = stubs.iter().map(|_| None).collect::<Vec<_>>();
+    for pair in order.windows(2) {
+      let (sender, receiver) = oneshot::channel();
+      nexts[pair[0]] = Some(sender);
+      turns[pair[1]] = Some(receiver);
+    }
+
+    let mut sources = IconSources::new();
+    for (((name, ranks, kinds, failed), turn), next) in stubs.into_iter().zip(turns).zip(nexts) {
+      sources.push(StubSource {
+        name,
+        ranks,
+        kinds,
+        failed,
+        turn: Mutex::new(turn),
+        next: Mutex::new(next),
+      });
+    }
+    sources
+  }
+
+  fn options(order: &[usize], avatar: &RepoIconKind, best_matches_only: bool) -> RepoIconsOptions {
+    RepoIconsOptions::new()
+      .sources(sources(order, avatar))
+      .best_matches_only(best_matches_only)
+  }
+
+  async fn loaded_best_match(
+    order: &[usize],
+    avatar: &RepoIconKind,
+    best_matches_only: bool,
+  ) -> RepoIcon {
+    let client = RepoIconsClient::builder()
+      .token(None::<String>)
+      .build()
+      .unwrap();
+    let options = options(order, avatar, best_matches_only);
+
+    RepoIcons::load_at(&client, "owner", "repo", None, None, &options)
+      .await
+      .icons
+      .unwrap()
+      .into_best_match()
+  }
+
+  async fn streamed_best_match(order: &[usize], avatar: &RepoIconKind) -> RepoIcon {
+    let client = RepoIconsClient::builder()
+      .token(None::<String>)
+      .build()
+      .unwrap();
+    let options = options(order, avatar, true);
+
+    let mut events = RepoIcons::stream_at(&client, "owner", "repo", None, None, &options);
+    let mut best: Option<RepoIcon> = None;
+
+    while let Some(event) = events.next().await {
+      if let RepoIconsEvent::Loaded { icons, .. } = event {
+        for icon in icons {
+          if best
+            .as_ref()
+            .map(|best| icon.kind < best.kind)
+            .unwrap_or(true)
+          {
+            best = Some(icon);
+          }
+        }
+      }
+    }
+
+    best.unwrap()
+  }
+
+  #[tokio::test]
+  async fn best_match_is_the_same_for_every_completion_order() {
+    let sources = 5;
+
+    for avatar in [RepoIconKind::OrgAvatar, RepoIconKind::Avatar] {
+      let expected = loaded_best_match(&(0..sources).collect::<Vec<_>>(), &avatar, false).await;
+
+      for order in (0..sources).permutations(sources) {
+        for best_matches_only in [false, true] {
+          let icon = loaded_best_match(&order, &avatar, best_matches_only).await;
+          assert_eq!(
+            (&icon.kind, &icon.url),
+            (&expected.kind, &expected.url),
+            "loaded in order {:?} with best_matches_only = {}",
+            order,
+            best_matches_only
+          );
+        }
+
+        let icon = streamed_best_match(&order, &avatar).await;
+        assert_eq!(
+          (&icon.kind, &icon.url),
+          (&expected.kind, &expected.url),
+          "streamed in order {:?}",
+          order
+        );
+      }
+    }
+  }
+
+  /// Rejects every candidate it's given, without picking any of them
+  struct RejectingSource(&'static [&'static str]);
+
+  impl IconSource for RejectingSource {
+    fn name(&self) -> &str {
+      "rejecting"
+    }
+
+    fn load<'a>(
+      &'a self,
+      ctx: &'a IconSourceContext<'a>,
+    ) -> BoxFuture<'a, Result<Vec<RepoIcon>, RepoIconsError>> {
+      async move {
+        if ctx.explain {
+          for candidate in self.0 {
+            ctx.reject(RejectedCandidate {
+              candidate: candidate.to_string(),
+              score: Default::default(),
+              reason: "no rules matched".to_string(),
+            });
+          }
+        }
+
+        Ok(Vec::new())
+      }
+      .boxed_maybe_send()
+    }
+  }
+
+  #[tokio::test]
+  async fn rejected_candidates_are_kept_when_nothing_is_picked() {
+    let client = RepoIconsClient::builder()
+      .token(None::<String>)
+      .build()
+      .unwrap();
+    let mut sources = IconSources::new();
+    sources
+      .push(RejectingSource(&["docs/diagram.png", "src/banner.jpg"]))
+      .push(RejectingSource(&["README.md"]));
+
+    let options = RepoIconsOptions::new().sources(sources).explain(true);
+    let result = RepoIcons::load_at(&client, "owner", "repo", None, None, &options).await;
+    assert!(matches!(result.icons, Err(RepoIconsError::NoIconsFound)));
+
+    let mut rejected = result
+      .rejected
+      .iter()
+      .map(|rejected| rejected.candidate.as_str())
+      .collect::<Vec<_>>();
+    rejected.sort();
+    assert_eq!(
+      rejected,
+      ["README.md", "docs/diagram.png", "src/banner.jpg"]
+    );
+  }
+
+  #[test]
+  fn pending_sources_that_could_rank_higher_block_the_best_match() {
+    let sources = sources(&[0, 1, 2, 3, 4], &RepoIconKind::Avatar);
+    let pending = sources.iter().collect::<Vec<_>>();
+
+    assert!(!is_best_match(RepoIconRank::Avatar, &pending));
+    // another avatar of the same rank could still load
+    assert!(!is_best_match(RepoIconRank::Avatar, &pending[1..]));
+    assert!(is_best_match(RepoIconRank::Avatar, &pending[2..]));
+    assert!(!is_best_match(RepoIconRank::ReadmeImage, &pending[2..]));
+    assert!(is_best_match(RepoIconRank::RepoFile, &pending[4..]));
+  }
+}
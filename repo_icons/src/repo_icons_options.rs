@@ -0,0 +1,150 @@
+use crate::{HomepageSource, IconSource, IconSources};
+use instant::Duration;
+use std::collections::{HashMap, HashSet};
+
+/// Options for [`RepoIcons::load_with_options`](crate::RepoIcons::load_with_options)
+///
+/// ```
+/// let options = RepoIconsOptions::new()
+///   .best_matches_only(true)
+///   .timeout(HomepageSource::NAME, Duration::from_secs(10))
+///   .deadline(Duration::from_secs(30));
+///
+/// let icons = RepoIcons::load_with_options("facebook", "react", &options).await;
+/// ```
+pub struct RepoIconsOptions {
+  pub(crate) sources: IconSources,
+  pub(crate) enabled_sources: Option<HashSet<String>>,
+  pub(crate) disabled_sources: HashSet<String>,
+  pub(crate) timeouts: HashMap<String, Duration>,
+  pub(crate) default_timeout: Option<Duration>,
+  pub(crate) deadline: Option<Duration>,
+  pub(crate) best_matches_only: bool,
+  pub(crate) explain: bool,
+  pub(crate) subpath: Option<String>,
+  pub(crate) git_ref: Option<String>,
+  pub(crate) concurrency: usize,
+}
+
+impl RepoIconsOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Replace the sources that get loaded
+  pub fn sources(mut self, sources: IconSources) -> Self {
+    self.sources = sources;
+    self
+  }
+
+  /// Add a source to the end of the list of sources
+  pub fn source<S: IconSource + 'static>(mut self, source: S) -> Self {
+    self.sources.push(source);
+    self
+  }
+
+  /// Only load the sources with the given names
+  pub fn enabled_sources<I, S>(mut self, names: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+  {
+    self.enabled_sources = Some(names.into_iter().map(Into::into).collect());
+    self
+  }
+
+  /// Skip loading the source with the given name
+  pub fn disable_source<S: Into<String>>(mut self, name: S) -> Self {
+    self.disabled_sources.insert(name.into());
+    self
+  }
+
+  /// Stop once the best match has been found, see [`RepoIcons::load`](crate::RepoIcons::load)
+  pub fn best_matches_only(mut self, best_matches_only: bool) -> Self {
+    self.best_matches_only = best_matches_only;
+    self
+  }
+
+  /// Attach an [`Explanation`](crate::Explanation) to every icon, describing why it
+  /// was ranked where it was, & list the candidates that weren't picked in
+  /// [`RepoIconsResult::rejected`](crate::RepoIconsResult::rejected)
+  pub fn explain(mut self, explain: bool) -> Self {
+    self.explain = explain;
+    self
+  }
+
+  /// Focus on a package inside of a monorepo, such as `packages/react-dom`.
+  /// Files under it are preferred, and its own `package.json` & readme are used
+  pub fn subpath<S: Into<String>>(mut self, subpath: S) -> Self {
+    let subpath = subpath.into().trim_matches('/').to_string();
+    self.subpath = Some(subpath).filter(|subpath| !subpath.is_empty());
+    self
+  }
+
+  /// Load the files & readme as of a branch, tag or commit,
+  /// instead of the default branch
+  pub fn git_ref<S: Into<String>>(mut self, git_ref: S) -> Self {
+    self.git_ref = Some(git_ref.into());
+    self
+  }
+
+  /// How many repos [`RepoIcons::load_many`](crate::RepoIcons::load_many)
+  /// loads at the same time
+  pub fn concurrency(mut self, concurrency: usize) -> Self {
+    self.concurrency = concurrency.max(1);
+    self
+  }
+
+  /// Give up on a source after the given duration
+  pub fn timeout<S: Into<String>>(mut self, name: S, timeout: Duration) -> Self {
+    self.timeouts.insert(name.into(), timeout);
+    self
+  }
+
+  /// Timeout for sources that don't have their own timeout set
+  pub fn default_timeout(mut self, timeout: Duration) -> Self {
+    self.default_timeout = Some(timeout);
+    self
+  }
+
+  /// Return whatever has been loaded after the given duration,
+  /// giving up on all the sources that haven't finished yet
+  pub fn deadline(mut self, deadline: Duration) -> Self {
+    self.deadline = Some(deadline);
+    self
+  }
+
+  pub fn is_enabled(&self, name: &str) -> bool {
+    !self.disabled_sources.contains(name)
+      && self
+        .enabled_sources
+        .as_ref()
+        .map(|enabled| enabled.contains(name))
+        .unwrap_or(true)
+  }
+
+  pub fn timeout_for(&self, name: &str) -> Option<Duration> {
+    self.timeouts.get(name).cloned().or(self.default_timeout)
+  }
+}
+
+impl Default for RepoIconsOptions {
+  fn default() -> Self {
+    let mut timeouts = HashMap::new();
+    timeouts.insert(HomepageSource::NAME.to_string(), Duration::from_secs(2));
+
+    Self {
+      sources: IconSources::default(),
+      enabled_sources: None,
+      disabled_sources: HashSet::new(),
+      timeouts,
+      default_timeout: None,
+      deadline: None,
+      best_matches_only: false,
+      explain: false,
+      subpath: None,
+      git_ref: None,
+      concurrency: 8,
+    }
+  }
+}
@@ -0,0 +1,483 @@
+use crate::{Forge, ForgeHost, RepoIconsError};
+use std::{
+  fmt::{self, Display},
+  str::FromStr,
+};
+use url::Url;
+
+/// A reference to a repo, parsed from any of the ways it's commonly written:
+///
+/// - `owner/repo` & `owner/repo@v1.0.0`
+/// - `github:owner/repo`, `gitlab:group/project`, `bitbucket:owner/repo` & `hf:owner/name`
+/// - `https://github.com/owner/repo/tree/main/packages/app`
+/// - `git+https://github.com/owner/repo.git#v1.0.0`
+/// - `git@github.com:owner/repo.git` & `ssh://git@github.com/owner/repo.git`
+///
+/// ```
+/// let slug: RepoSlug = "git@gitlab.com:group/subgroup/project.git".parse()?;
+/// assert_eq!(slug.owner, "group/subgroup");
+/// assert_eq!(slug.repo, "project");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoSlug {
+  /// The website of the forge, `None` when it wasn't part of the slug
+  pub host: Option<Url>,
+  pub owner: String,
+  pub repo: String,
+  /// The branch, tag or commit of tree & blob urls, or after a `@` or `#`
+  pub commitish: Option<String>,
+  /// The path inside of the repo of tree & blob urls
+  pub path: Option<String>,
+}
+
+impl RepoSlug {
+  pub fn new<O: Into<String>, R: Into<String>>(owner: O, repo: R) -> Self {
+    Self {
+      host: None,
+      owner: owner.into(),
+      repo: repo.into(),
+      commitish: None,
+      path: None,
+    }
+  }
+
+  /// The forge the slug points to, hosts that aren't well known
+  /// are assumed to be GitHub Enterprise Servers
+  pub fn forge_host(&self) -> Option<ForgeHost> {
+    let web = self.host.clone()?;
+    Some(ForgeHost::detect(&web).unwrap_or_else(|| ForgeHost::github_enterprise(web)))
+  }
+
+  /// Whether the slug points to github.com, which plain `owner/repo` slugs do
+  pub fn is_github_com(&self) -> bool {
+    self
+      .forge_host()
+      .map(|host| host.is_github_com())
+      .unwrap_or(true)
+  }
+}
+
+impl Display for RepoSlug {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}/{}", self.owner, self.repo)
+  }
+}
+
+impl FromStr for RepoSlug {
+  type Err = RepoIconsError;
+
+  fn from_str(slug: &str) -> Result<Self, Self::Err> {
+    let invalid = || RepoIconsError::other(format!("invalid repo slug {}", slug));
+
+    // npm & pip put the branch, tag or commit after a #
+    let (slug, fragment) = match slug.trim().split_once('#') {
+      Some((slug, fragment)) => (slug, Some(fragment).filter(|f| !f.is_empty())),
+      None => (slug.trim(), None),
+    };
+
+    let (host, path) = if let Some(shorthand) = regex!("^(github|gitlab|bitbucket|hf):(?!//)(.+)")
+      .captures(slug)
+      .unwrap()
+    {
+      let host = match &shorthand[1] {
+        "github" => ForgeHost::github(),
+        "gitlab" => ForgeHost::public(Forge::Gitlab),
+        "bitbucket" => ForgeHost::bitbucket(),
+        _ => ForgeHost::hugging_face(),
+      };
+
+      (Some(host.web().clone()), shorthand[2].to_string())
+    } else if regex!(r"^[\w.+-]+://").is_match(slug).unwrap() {
+      let url = Url::parse(slug.strip_prefix("git+").unwrap_or(slug)).map_err(|_| invalid())?;
+      let host = url.host_str().ok_or_else(invalid)?;
+
+      // ssh & git urls are served over https on the website
+      let web = match (url.scheme(), url.port()) {
+        ("http" | "https", Some(port)) => format!("{}://{}:{}/", url.scheme(), host, port),
+        ("http" | "https", None) => format!("{}://{}/", url.scheme(), host),
+        _ => format!("https://{}/", host),
+      };
+
+      (
+        Some(Url::parse(&web).map_err(|_| invalid())?),
+        url.path().to_string(),
+      )
+    } else if let Some(scp) = regex!(r"^(?:[^@/\s]+@)?([^:/\s]+):(.+)")
+      .captures(slug)
+      .unwrap()
+    {
+      let web = Url::parse(&format!("https://{}/", &scp[1])).map_err(|_| invalid())?;
+      (Some(web), scp[2].to_string())
+    } else {
+      (None, slug.to_string())
+    };
+
+    let segments = path
+      .split('/')
+      .filter(|segment| !segment.is_empty())
+      .collect::<Vec<_>>();
+
+    let forge = host
+      .as_ref()
+      .and_then(ForgeHost::detect)
+      .map(|host| host.forge());
+
+    // GitLab separates the project from the rest of the url with /-/,
+    // and allows projects to be nested in any number of groups
+    let marker = segments.iter().position(|segment| *segment == "-");
+    let repo_len = match (marker, forge) {
+      (Some(marker), _) => marker,
+      (None, Some(Forge::Gitlab)) => segments.len(),
+      (None, Some(Forge::HuggingFace))
+        if matches!(segments.first(), Some(&"spaces") | Some(&"datasets")) =>
+      {
+        3
+      }
+      (None, _) => 2,
+    };
+
+    if repo_len < 2 || segments.len() < repo_len {
+      return Err(invalid());
+    }
+
+    let owner = segments[..repo_len - 1].join("/");
+    let (repo, at_ref) = match segments[repo_len - 1].split_once('@') {
+      Some((repo, at_ref)) => (repo, Some(at_ref).filter(|at_ref| !at_ref.is_empty())),
+      None => (segments[repo_len - 1], None),
+    };
+    let repo = repo.trim_end_matches(".git").to_string();
+    if repo.is_empty() {
+      return Err(invalid());
+    }
+
+    let mut rest = segments[repo_len..].iter().copied().peekable();
+    rest.next_if_eq(&"-");
+
+    let (commitish, path) = match rest.next() {
+      Some("tree" | "blob" | "raw" | "src" | "resolve") => {
+        // Gitea puts the kind of ref before it
+        rest.next_if(|segment| matches!(*segment, "branch" | "tag" | "commit"));
+
+        let commitish = rest.next().map(|commitish| commitish.to_string());
+        let path = rest.collect::<Vec<_>>().join("/");
+
+        (commitish, Some(path).filter(|path| !path.is_empty()))
+      }
+      _ => (None, None),
+    };
+
+    Ok(Self {
+      host,
+      owner,
+      repo,
+      commitish: commitish
+        .or(at_ref.map(|at_ref| at_ref.to_string()))
+        .or_else(|| fragment.map(|fragment| fragment.to_string())),
+      path,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The host, owner, repo, commitish & path of the slug
+  fn parse(
+    slug: &str,
+  ) -> (
+    Option<String>,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+  ) {
+    let slug = slug
+      .parse::<RepoSlug>()
+      .unwrap_or_else(|err| panic!("{}: {}", slug, err));
+
+    (
+      slug.host.map(|host| host.to_string()),
+      slug.owner,
+      slug.repo,
+      slug.commitish,
+      slug.path,
+    )
+  }
+
+  fn expected(
+    host: Option<&str>,
+    owner: &str,
+    repo: &str,
+    commitish: Option<&str>,
+    path: Option<&str>,
+  ) -> (
+    Option<String>,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+  ) {
+    (
+      host.map(String::from),
+      owner.to_string(),
+      repo.to_string(),
+      commitish.map(String::from),
+      path.map(String::from),
+    )
+  }
+
+  const GITHUB: Option<&str> = Some("https://github.com/");
+
+  #[test]
+  fn shorthand() {
+    assert_eq!(
+      parse("facebook/react"),
+      expected(None, "facebook", "react", None, None)
+    );
+    assert_eq!(
+      parse("  facebook/react.git  "),
+      expected(None, "facebook", "react", None, None)
+    );
+  }
+
+  #[test]
+  fn at_ref() {
+    assert_eq!(
+      parse("facebook/react@v18.2.0"),
+      expected(None, "facebook", "react", Some("v18.2.0"), None)
+    );
+  }
+
+  #[test]
+  fn hash_ref() {
+    assert_eq!(
+      parse("facebook/react#main"),
+      expected(None, "facebook", "react", Some("main"), None)
+    );
+  }
+
+  #[test]
+  fn forge_shorthand() {
+    assert_eq!(
+      parse("github:facebook/react"),
+      expected(GITHUB, "facebook", "react", None, None)
+    );
+    assert_eq!(
+      parse("gitlab:gitlab-org/ci-cd/gitlab-runner"),
+      expected(
+        Some("https://gitlab.com/"),
+        "gitlab-org/ci-cd",
+        "gitlab-runner",
+        None,
+        None
+      )
+    );
+    assert_eq!(
+      parse("bitbucket:atlassian/python-bitbucket"),
+      expected(
+        Some("https://bitbucket.org/"),
+        "atlassian",
+        "python-bitbucket",
+        None,
+        None
+      )
+    );
+    assert_eq!(
+      parse("hf:gradio/hello_world"),
+      expected(
+        Some("https://huggingface.co/"),
+        "gradio",
+        "hello_world",
+        None,
+        None
+      )
+    );
+  }
+
+  #[test]
+  fn https_url() {
+    assert_eq!(
+      parse("https://github.com/facebook/react"),
+      expected(GITHUB, "facebook", "react", None, None)
+    );
+    assert_eq!(
+      parse("https://github.com/facebook/react.git"),
+      expected(GITHUB, "facebook", "react", None, None)
+    );
+  }
+
+  #[test]
+  fn git_plus_https_url() {
+    assert_eq!(
+      parse("git+https://github.com/facebook/react.git#v18.2.0"),
+      expected(GITHUB, "facebook", "react", Some("v18.2.0"), None)
+    );
+  }
+
+  #[test]
+  fn scp_url() {
+    assert_eq!(
+      parse("git@github.com:facebook/react.git"),
+      expected(GITHUB, "facebook", "react", None, None)
+    );
+  }
+
+  #[test]
+  fn ssh_url() {
+    assert_eq!(
+      parse("ssh://git@github.com/facebook/react.git"),
+      expected(GITHUB, "facebook", "react", None, None)
+    );
+  }
+
+  #[test]
+  fn tree_url() {
+    assert_eq!(
+      parse("https://github.com/facebook/react/tree/main/packages/react-dom"),
+      expected(
+        GITHUB,
+        "facebook",
+        "react",
+        Some("main"),
+        Some("packages/react-dom")
+      )
+    );
+  }
+
+  #[test]
+  fn blob_url() {
+    assert_eq!(
+      parse("https://github.com/facebook/react/blob/v18.2.0/README.md"),
+      expected(
+        GITHUB,
+        "facebook",
+        "react",
+        Some("v18.2.0"),
+        Some("README.md")
+      )
+    );
+  }
+
+  #[test]
+  fn src_url() {
+    assert_eq!(
+      parse("https://bitbucket.org/atlassian/python-bitbucket/src/master/README.md"),
+      expected(
+        Some("https://bitbucket.org/"),
+        "atlassian",
+        "python-bitbucket",
+        Some("master"),
+        Some("README.md")
+      )
+    );
+    // Gitea puts the kind of ref before it
+    assert_eq!(
+      parse("https://codeberg.org/forgejo/forgejo/src/branch/forgejo/docs"),
+      expected(
+        Some("https://codeberg.org/"),
+        "forgejo",
+        "forgejo",
+        Some("forgejo"),
+        Some("docs")
+      )
+    );
+  }
+
+  #[test]
+  fn resolve_url() {
+    assert_eq!(
+      parse("https://huggingface.co/google-bert/bert-base-uncased/resolve/main/config.json"),
+      expected(
+        Some("https://huggingface.co/"),
+        "google-bert",
+        "bert-base-uncased",
+        Some("main"),
+        Some("config.json")
+      )
+    );
+  }
+
+  #[test]
+  fn gitlab_subgroups() {
+    assert_eq!(
+      parse("https://gitlab.com/gitlab-org/ci-cd/gitlab-runner/-/tree/main/docs"),
+      expected(
+        Some("https://gitlab.com/"),
+        "gitlab-org/ci-cd",
+        "gitlab-runner",
+        Some("main"),
+        Some("docs")
+      )
+    );
+    assert_eq!(
+      parse("git@gitlab.com:group/subgroup/project.git"),
+      expected(
+        Some("https://gitlab.com/"),
+        "group/subgroup",
+        "project",
+        None,
+        None
+      )
+    );
+  }
+
+  #[test]
+  fn hugging_face_spaces_and_datasets() {
+    assert_eq!(
+      parse("https://huggingface.co/spaces/gradio/hello_world"),
+      expected(
+        Some("https://huggingface.co/"),
+        "spaces/gradio",
+        "hello_world",
+        None,
+        None
+      )
+    );
+    assert_eq!(
+      parse("https://huggingface.co/datasets/rajpurkar/squad/tree/main"),
+      expected(
+        Some("https://huggingface.co/"),
+        "datasets/rajpurkar",
+        "squad",
+        Some("main"),
+        None
+      )
+    );
+  }
+
+  #[test]
+  fn other_hosts() {
+    let slug = "https://github.example.com/team/app"
+      .parse::<RepoSlug>()
+      .unwrap();
+    assert_eq!(
+      slug.forge_host(),
+      Some(ForgeHost::github_enterprise(
+        Url::parse("https://github.example.com/").unwrap()
+      ))
+    );
+    assert!(!slug.is_github_com());
+
+    assert_eq!(
+      parse("http://localhost:3000/owner/repo"),
+      expected(Some("http://localhost:3000/"), "owner", "repo", None, None)
+    );
+  }
+
+  #[test]
+  fn rejects_invalid_slugs() {
+    for slug in [
+      "",
+      "react",
+      "facebook/",
+      "facebook/.git",
+      "github:facebook",
+      "https://github.com/facebook",
+      "git@github.com:facebook",
+      "https://gitlab.com/-/tree/main",
+    ] {
+      assert!(slug.parse::<RepoSlug>().is_err(), "{:?} was parsed", slug);
+    }
+  }
+}
@@ -0,0 +1,159 @@
+//! Loads real repos from responses recorded from the GitHub API,
+//! so that the ranking can be tested without the network. Homepages
+//! are scraped by `site_icons`, so only the icons found on them are
+//! recorded, not the pages themselves.
+//!
+//! The fixtures are also used by `repo-icons eval`. They're committed as
+//! [`RecordBackend`] wrote them, so never edit them by hand. Record them
+//! after changing which requests are made, or to check the ranking against
+//! the repos of today:
+//!
+//! ```sh
+//! REPO_ICONS_RECORD=1 GITHUB_TOKEN=... cargo test --test fixtures
+//! ```
+//!
+//! Repos whose fixtures haven't been recorded yet are skipped.
+
+use repo_icons::{
+  FixtureBackend, Framework, RecordBackend, RepoFile, RepoIcon, RepoIconKind, RepoIconsClient,
+  RepoIconsOptions,
+};
+use std::{env, path::Path};
+use url::Url;
+
+/// `None` when the fixtures haven't been recorded
+fn client(fixtures: &str) -> Option<RepoIconsClient> {
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+    .join("tests/fixtures")
+    .join(fixtures);
+
+  let builder = RepoIconsClient::builder();
+  let builder = if env::var_os("REPO_ICONS_RECORD").is_some() {
+    builder
+      .token(env::var("GITHUB_TOKEN").ok())
+      .backend(RecordBackend::new(dir))
+  } else if dir.is_dir() {
+    builder
+      .token(None::<String>)
+      .backend(FixtureBackend::new(dir))
+  } else {
+    eprintln!("skipping {}, its fixtures haven't been recorded", fixtures);
+    return None;
+  };
+
+  Some(builder.build().unwrap())
+}
+
+async fn load(owner: &str, repo: &str) -> Option<Vec<RepoIcon>> {
+  load_slug(
+    &format!("{}-{}", owner, repo),
+    &format!("{}/{}", owner, repo),
+  )
+  .await
+}
+
+async fn load_slug(fixtures: &str, slug: &str) -> Option<Vec<RepoIcon>> {
+  let result = client(fixtures)?
+    .load_slug(slug, &RepoIconsOptions::new())
+    .await;
+
+  for error in result.errors.iter().flatten() {
+    eprintln!("{}: {}", slug, error);
+  }
+
+  Some(
+    result
+      .icons
+      .unwrap_or_else(|err| panic!("{}: {}", slug, err))
+      .to_vec(),
+  )
+}
+
+/// Files of public repos are downloaded from raw.githubusercontent.com
+fn raw_url(file: &RepoFile) -> String {
+  format!(
+    "https://raw.githubusercontent.com/{}/{}/{}",
+    file.github, file.commit_sha, file.path
+  )
+}
+
+#[tokio::test]
+async fn package_json_icon() {
+  let icons = match load("microsoft", "vscode-eslint").await {
+    Some(icons) => icons,
+    None => return,
+  };
+  let icon = &icons[0];
+
+  match &icon.kind {
+    RepoIconKind::IconField { file } => {
+      assert_eq!(file.path, "eslint_icon.png");
+      assert_eq!(icon.url.as_str(), raw_url(file));
+    }
+    kind => panic!("expected the package.json icon, got {}", kind),
+  }
+}
+
+#[tokio::test]
+async fn framework_default_favicon() {
+  // the template outweighs the logo of the docs inside of its subpath
+  let icons = match load_slug(
+    "facebook-create-react-app",
+    "https://github.com/facebook/create-react-app/tree/main/packages/cra-template",
+  )
+  .await
+  {
+    Some(icons) => icons,
+    None => return,
+  };
+  let path = "packages/cra-template/template/public/favicon.ico";
+
+  let icon = icons
+    .iter()
+    .find(|icon| matches!(&icon.kind, RepoIconKind::Framework { file, .. } if file.path == path))
+    .unwrap_or_else(|| panic!("{} wasn't recognized as a framework icon", path));
+
+  match &icon.kind {
+    RepoIconKind::Framework { framework, file } => {
+      assert_eq!(*framework, Framework::CreateReactApp);
+      assert_eq!(icon.url.as_str(), raw_url(file));
+    }
+    _ => unreachable!(),
+  }
+}
+
+#[tokio::test]
+async fn avatar_of_org_with_the_same_name() {
+  let icons = match load("denoland", "deno").await {
+    Some(icons) => icons,
+    None => return,
+  };
+  let icon = &icons[0];
+
+  assert_eq!(icon.kind, RepoIconKind::Avatar);
+  assert_eq!(icon.url.as_str(), "https://github.com/denoland.png");
+}
+
+#[tokio::test]
+async fn homepage_icons() {
+  let icons = match load("facebook", "create-react-app").await {
+    Some(icons) => icons,
+    None => return,
+  };
+  let homepage = Url::parse("https://create-react-app.dev/").unwrap();
+
+  assert_eq!(
+    icons[0].kind,
+    RepoIconKind::SiteFavicon {
+      homepage: homepage.clone()
+    }
+  );
+  assert_eq!(
+    icons[0].url.as_str(),
+    "https://create-react-app.dev/img/favicon/favicon.ico"
+  );
+  assert!(icons.iter().any(|icon| icon.kind
+    == RepoIconKind::SiteLogo {
+      homepage: homepage.clone()
+    }));
+}
@@ -0,0 +1,52 @@
+//! Decodes the sample images in `tests/images`
+//!
+//! ```sh
+//! cargo test --features image --test image
+//! ```
+
+#![cfg(feature = "image")]
+
+use image::GenericImageView;
+use repo_icons::decode_image;
+
+fn dimensions(data: &[u8]) -> (u32, u32) {
+  decode_image(data).unwrap().dimensions()
+}
+
+#[test]
+fn png() {
+  assert_eq!(dimensions(include_bytes!("images/python.png")), (16, 16));
+}
+
+#[test]
+fn jpeg() {
+  assert_eq!(dimensions(include_bytes!("images/python.jpg")), (16, 16));
+}
+
+#[test]
+fn gif() {
+  assert_eq!(dimensions(include_bytes!("images/python.gif")), (16, 16));
+}
+
+#[test]
+fn webp() {
+  assert_eq!(dimensions(include_bytes!("images/python.webp")), (16, 16));
+}
+
+/// 16x16, 32x32 & 48x48 bitmaps, along with a 256x256 PNG
+#[test]
+fn largest_ico_image() {
+  assert_eq!(dimensions(include_bytes!("images/favicon.ico")), (256, 256));
+}
+
+#[test]
+fn svg_is_unsupported() {
+  let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16"></svg>"#;
+  assert!(decode_image(svg).is_err());
+}
+
+#[test]
+fn truncated_ico() {
+  let ico = include_bytes!("images/favicon.ico");
+  assert!(decode_image(&ico[..100]).is_err());
+}
@@ -0,0 +1,275 @@
+//! Retries, backoff & rate limits of requests, against a local
+//! mock of the GitHub API that replies with a script of responses
+
+use repo_icons::{ForgeHost, RepoIconsClient, RepoIconsError, RetryPolicy};
+use serde_json::json;
+use std::{
+  sync::{Arc, Mutex},
+  time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+  io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+  net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+#[derive(Clone)]
+struct MockResponse {
+  status: u16,
+  headers: Vec<(&'static str, String)>,
+  body: String,
+}
+
+impl MockResponse {
+  fn new(status: u16, body: &str) -> Self {
+    Self {
+      status,
+      headers: Vec::new(),
+      body: body.to_string(),
+    }
+  }
+
+  fn repo() -> Self {
+    let body = json!({
+      "owner": { "login": "facebook", "type": "Organization" },
+      "name": "react",
+      "default_branch": "main",
+      "private": false,
+      "homepage": null,
+    });
+
+    Self::new(200, &body.to_string())
+  }
+
+  fn header(mut self, name: &'static str, value: impl ToString) -> Self {
+    self.headers.push((name, value.to_string()));
+    self
+  }
+}
+
+/// The requests the mock received
+#[derive(Clone, Default)]
+struct Requests(Arc<Mutex<Vec<Instant>>>);
+
+impl Requests {
+  fn len(&self) -> usize {
+    self.0.lock().unwrap().len()
+  }
+
+  /// Time between each request & the one before it
+  fn gaps(&self) -> Vec<Duration> {
+    let times = self.0.lock().unwrap();
+    times.windows(2).map(|pair| pair[1] - pair[0]).collect()
+  }
+}
+
+/// A GitHub Enterprise Server that replies with each of the responses in
+/// turn, repeating the last one once it runs out
+async fn start_mock_server(responses: Vec<MockResponse>) -> (Url, Requests) {
+  let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+  let requests = Requests::default();
+
+  let received = requests.clone();
+  tokio::spawn(async move {
+    loop {
+      let (socket, _) = listener.accept().await.unwrap();
+      tokio::spawn(serve(socket, responses.clone(), received.clone()));
+    }
+  });
+
+  (url, requests)
+}
+
+async fn serve(socket: TcpStream, responses: Vec<MockResponse>, requests: Requests) {
+  let (reader, mut writer) = socket.into_split();
+  let mut reader = BufReader::new(reader);
+
+  loop {
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+      return;
+    }
+
+    // GET requests don't have a body, so the request ends with the headers
+    loop {
+      let mut header = String::new();
+      if reader.read_line(&mut header).await.unwrap_or(0) == 0 {
+        return;
+      }
+      if header == "\r\n" {
+        break;
+      }
+    }
+
+    let response = {
+      let mut requests = requests.0.lock().unwrap();
+      requests.push(Instant::now());
+      &responses[(requests.len() - 1).min(responses.len() - 1)]
+    };
+
+    let mut head = format!("HTTP/1.1 {} Mock\r\n", response.status);
+    for (name, value) in &response.headers {
+      head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!(
+      "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+      response.body.len()
+    ));
+
+    if writer
+      .write_all((head + &response.body).as_bytes())
+      .await
+      .is_err()
+    {
+      return;
+    }
+  }
+}
+
+fn client(url: &Url) -> RepoIconsClient {
+  RepoIconsClient::builder()
+    .token(None::<String>)
+    .host(ForgeHost::github_enterprise(url.clone()))
+    .retry_policy(RetryPolicy {
+      max_retries: 3,
+      base_delay: Duration::from_millis(50),
+      max_delay: Duration::from_secs(2),
+    })
+    .build()
+    .unwrap()
+}
+
+fn in_an_hour() -> u64 {
+  SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .unwrap()
+    .as_secs()
+    + 3600
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_with_backoff() {
+  let (url, requests) = start_mock_server(vec![
+    MockResponse::new(502, "bad gateway"),
+    MockResponse::new(503, "unavailable"),
+    MockResponse::repo(),
+  ])
+  .await;
+
+  let repo = client(&url).repo("facebook", "react").await.unwrap();
+  assert_eq!(repo.name, "react");
+  assert_eq!(requests.len(), 3);
+
+  // 50ms & then 100ms, with up to half of it taken off by the jitter
+  let gaps = requests.gaps();
+  assert!(gaps[0] >= Duration::from_millis(25), "{:?}", gaps);
+  assert!(gaps[1] >= Duration::from_millis(50), "{:?}", gaps);
+}
+
+#[tokio::test]
+async fn server_errors_give_up_after_the_max_retries() {
+  let (url, requests) = start_mock_server(vec![MockResponse::new(500, "oops")]).await;
+
+  let err = client(&url).repo("facebook", "react").await.err().unwrap();
+  assert!(
+    matches!(err, RepoIconsError::Http { status: 500, .. }),
+    "{:?}",
+    err
+  );
+  assert_eq!(requests.len(), 4);
+}
+
+#[tokio::test]
+async fn secondary_rate_limits_wait_for_retry_after() {
+  let (url, requests) = start_mock_server(vec![
+    MockResponse::new(403, "You have exceeded a secondary rate limit").header("retry-after", 1),
+    MockResponse::repo(),
+  ])
+  .await;
+
+  client(&url).repo("facebook", "react").await.unwrap();
+  assert_eq!(requests.len(), 2);
+  assert!(requests.gaps()[0] >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn secondary_rate_limits_longer_than_the_max_delay_fail() {
+  let (url, requests) = start_mock_server(vec![MockResponse::new(
+    403,
+    "You have exceeded a secondary rate limit",
+  )
+  .header("retry-after", 60)])
+  .await;
+
+  let err = client(&url).repo("facebook", "react").await.err().unwrap();
+  assert!(
+    matches!(err, RepoIconsError::RateLimited { .. }),
+    "{:?}",
+    err
+  );
+  assert_eq!(requests.len(), 1);
+}
+
+#[tokio::test]
+async fn exhausted_budget_fails_fast() {
+  let reset = in_an_hour();
+  let (url, requests) = start_mock_server(vec![MockResponse::repo()
+    .header("x-ratelimit-remaining", 0)
+    .header("x-ratelimit-reset", reset)])
+  .await;
+
+  let client = client(&url);
+  client.repo("facebook", "react").await.unwrap();
+
+  let rate_limit = client.rate_limit().unwrap();
+  assert_eq!((rate_limit.remaining, rate_limit.reset), (0, reset));
+
+  let err = client.repo("facebook", "jest").await.err().unwrap();
+  assert_eq!(
+    err,
+    RepoIconsError::RateLimited {
+      url: Some(url.join("api/v3/repos/facebook/jest").unwrap()),
+      reset: Some(reset),
+    }
+  );
+  assert_eq!(requests.len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limit_responses_are_rate_limited_errors() {
+  let reset = in_an_hour();
+  let cases = [
+    (
+      MockResponse::new(429, "too many requests")
+        .header("x-ratelimit-remaining", 0)
+        .header("x-ratelimit-reset", reset),
+      Some(reset),
+    ),
+    (
+      MockResponse::new(403, "API rate limit exceeded for 127.0.0.1"),
+      None,
+    ),
+  ];
+
+  for (response, reset) in cases {
+    let (url, _) = start_mock_server(vec![response]).await;
+
+    let err = client(&url).repo("facebook", "react").await.err().unwrap();
+    assert_eq!(
+      err,
+      RepoIconsError::RateLimited {
+        url: Some(url.join("api/v3/repos/facebook/react").unwrap()),
+        reset,
+      }
+    );
+  }
+
+  let (url, _) = start_mock_server(vec![MockResponse::new(403, "Resource not accessible")]).await;
+  let err = client(&url).repo("facebook", "react").await.err().unwrap();
+  assert!(
+    matches!(err, RepoIconsError::Http { status: 403, .. }),
+    "{:?}",
+    err
+  );
+}
@@ -19,6 +19,7 @@ const PRETTY_KINDS: Record<Icon['kind'], string> = {
   site_logo: `Auto-detected logo on repo's homepage`,
   repo_file: `Files within repo`,
   avatar: `Repo owner's Avatar`,
+  space_emoji: `Hugging Face Space emoji`,
   framework_icon: `Framework Icon`,
   org_avatar: `Organization's Avatar`,
   user_avatar_fallback: `User's Avatar (fallback)`,
@@ -22,6 +22,7 @@ export type Icon = IconInfo & {
   kind:
     | 'icon_field'
     | 'avatar'
+    | 'space_emoji'
     | 'org_avatar'
     | 'user_avatar_fallback'
     | 'app_icon'